@@ -0,0 +1,76 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use nautilus_server::common::current_timestamp_ms;
+use nitro_verifier::{verify_attestation, VerifiedAttestation, VerifyOptions};
+use serde_json::json;
+
+#[derive(Args)]
+pub struct VerifyAttestationArgs {
+    /// File holding the attestation as hex, or the JSON body returned by
+    /// `/get_attestation`. Use `-` for stdin.
+    input: String,
+    /// PEM file of an alternative trusted root (defaults to the bundled AWS Nitro root).
+    #[arg(long)]
+    root: Option<String>,
+    /// Hex encoded nonce the document must carry.
+    #[arg(long)]
+    nonce: Option<String>,
+    /// Reject documents older than this many seconds.
+    #[arg(long)]
+    max_age_secs: Option<u64>,
+    /// Verify as of this Unix time in milliseconds instead of now.
+    #[arg(long)]
+    at_ms: Option<u64>,
+}
+
+pub fn run(args: VerifyAttestationArgs) -> Result<()> {
+    let document = decode_document(&crate::read_input(&args.input)?)?;
+
+    let mut options = VerifyOptions::new(args.at_ms.unwrap_or_else(current_timestamp_ms));
+    options.max_age_ms = args.max_age_secs.map(|secs| secs.saturating_mul(1_000));
+    if let Some(nonce) = &args.nonce {
+        options.expected_nonce =
+            Some(hex::decode(nonce.trim_start_matches("0x")).context("invalid --nonce")?);
+    }
+    if let Some(root) = &args.root {
+        options = options.with_root_pem(&crate::read_input(root)?)?;
+    }
+
+    let verified = verify_attestation(&document, &options)?;
+    println!("{}", serde_json::to_string_pretty(&to_json(&verified))?);
+    Ok(())
+}
+
+/// Accepts either bare hex or the `{"attestation": "<hex>"}` JSON body.
+fn decode_document(input: &str) -> Result<Vec<u8>> {
+    let input = input.trim();
+    let hex_doc = match serde_json::from_str::<serde_json::Value>(input) {
+        Ok(value) => value
+            .get("attestation")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("JSON input has no `attestation` field"))?
+            .to_string(),
+        Err(_) => input.to_string(),
+    };
+    hex::decode(hex_doc.trim_start_matches("0x")).context("attestation is not valid hex")
+}
+
+fn to_json(verified: &VerifiedAttestation) -> serde_json::Value {
+    let pcrs: serde_json::Map<String, serde_json::Value> = verified
+        .pcrs
+        .iter()
+        .map(|(index, value)| (index.to_string(), json!(hex::encode(value))))
+        .collect();
+    json!({
+        "module_id": verified.module_id,
+        "digest": verified.digest,
+        "timestamp_ms": verified.timestamp_ms,
+        "pcrs": pcrs,
+        "public_key": verified.public_key.as_ref().map(hex::encode),
+        "user_data": verified.user_data.as_ref().map(hex::encode),
+        "nonce": verified.nonce.as_ref().map(hex::encode),
+    })
+}
@@ -0,0 +1,398 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use nautilus_server::app::{
+    encrypt_config, EnclaveConfigError, FileKycConfig, KycRuntime, SealConfig,
+};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Longest value printed by `diff-config` before it is shortened.
+const MAX_DIFF_VALUE_LEN: usize = 80;
+
+#[derive(Args)]
+pub struct ValidateConfigArgs {
+    /// KYC config as YAML or JSON. Use `-` for stdin.
+    config: String,
+    /// Fail on lint warnings too.
+    #[arg(long)]
+    strict: bool,
+}
+
+#[derive(Args)]
+pub struct DiffConfigArgs {
+    /// Current config.
+    old: String,
+    /// Proposed config.
+    new: String,
+}
+
+#[derive(Args)]
+pub struct EncryptConfigArgs {
+    /// KYC config as YAML or JSON. Use `-` for stdin.
+    config: String,
+    /// Hex encoded Seal key id, as later passed to `/seal/init_parameter_load`.
+    #[arg(long)]
+    id: String,
+    /// Key servers that must approve decryption (defaults to all of them).
+    #[arg(long)]
+    threshold: Option<u8>,
+    /// Seal config listing the key servers, i.e. the `seal_config.yaml` the
+    /// enclave image was built with.
+    #[arg(long)]
+    seal_config: String,
+}
+
+/// Parses and validates a config the way the enclave does, printing every
+/// violation with its config path.
+fn load(path: &str) -> Result<(String, FileKycConfig, KycRuntime)> {
+    let raw = crate::read_input(path)?;
+    let config = FileKycConfig::parse(&raw).map_err(|e| anyhow!("{}: {}", path, e))?;
+    match KycRuntime::try_from(config.clone()) {
+        Ok(runtime) => Ok((raw, config, runtime)),
+        Err(EnclaveConfigError::Invalid(violations)) => {
+            for violation in &violations {
+                eprintln!("error: {}: {}", violation.field, violation.message);
+            }
+            bail!("{}: {} config error(s)", path, violations.len())
+        }
+        Err(e) => bail!("{}: {}", path, e),
+    }
+}
+
+fn commitment_hex(runtime: &KycRuntime) -> String {
+    hex::encode(runtime.commitment().digest())
+}
+
+/// Checks that parse fine but are likely mistakes, as `(path, message)`.
+fn lint(config: &FileKycConfig) -> Vec<(String, String)> {
+    let mut warnings = Vec::new();
+    let mut warn = |path: String, message: &str| warnings.push((path, message.to_string()));
+
+    if config.version == 0 {
+        warn(
+            "version".to_string(),
+            "is 0; set a revision so attestations identify this config",
+        );
+    }
+    if config
+        .enclave_measurement
+        .trim_start_matches("0x")
+        .chars()
+        .all(|c| c == '0')
+    {
+        warn(
+            "enclave_measurement".to_string(),
+            "is all zeros; only a dev-attestor build measures that",
+        );
+    }
+    if !config.walrus_aggregator_url.starts_with("https://") {
+        warn("walrus_aggregator_url".to_string(), "is not https");
+    }
+    for (index, provider) in config.providers.iter().enumerate() {
+        let path = |field: &str| format!("providers[{}].{}", index, field);
+        if provider.allow_unsigned {
+            warn(
+                path("allow_unsigned"),
+                "accepts payloads without providerSignature",
+            );
+        }
+        if provider
+            .webhook_url
+            .as_ref()
+            .is_some_and(|url| !url.starts_with("https://"))
+        {
+            warn(path("webhook_url"), "is not https");
+        }
+    }
+    if config.sanctions_list.is_none() {
+        warn(
+            "sanctions_list".to_string(),
+            "is not set; sanctions screening is off",
+        );
+    }
+    warnings
+}
+
+pub fn validate(args: ValidateConfigArgs) -> Result<()> {
+    let (_, config, runtime) = load(&args.config)?;
+    let warnings = lint(&config);
+    for (path, message) in &warnings {
+        eprintln!("warning: {}: {}", path, message);
+    }
+    if args.strict && !warnings.is_empty() {
+        bail!("{} lint warning(s)", warnings.len());
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "version": runtime.version,
+            "providers": runtime.providers.len(),
+            "config_hash": hex::encode(runtime.config_hash),
+            "commitment_digest": commitment_hex(&runtime),
+        }))?
+    );
+    Ok(())
+}
+
+/// Flattens a config into `path -> value`, leaving out empty lists. Lists of
+/// objects are keyed by `provider_id` or `name` so reordering them is not reported
+/// as a change.
+fn flatten(path: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                flatten(&join(key), field, out);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let key = item
+                    .get("provider_id")
+                    .or_else(|| item.get("name"))
+                    .and_then(Value::as_str)
+                    .map(|id| format!("{}[{}]", path, id))
+                    .unwrap_or_else(|| format!("{}[{}]", path, index));
+                flatten(&key, item, out);
+            }
+        }
+        _ => {
+            out.insert(path.to_string(), value.clone());
+        }
+    }
+}
+
+fn display(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() > MAX_DIFF_VALUE_LEN {
+        let prefix: String = text.chars().take(MAX_DIFF_VALUE_LEN).collect();
+        format!("{}... ({} chars)", prefix, text.chars().count())
+    } else {
+        text
+    }
+}
+
+/// Lines describing how `new` differs from `old`: `-` removed, `~` changed and
+/// `+` added, each with its flattened path.
+fn changes(old: &FileKycConfig, new: &FileKycConfig) -> Result<Vec<String>> {
+    let (mut old_fields, mut new_fields) = (BTreeMap::new(), BTreeMap::new());
+    flatten("", &serde_json::to_value(old)?, &mut old_fields);
+    flatten("", &serde_json::to_value(new)?, &mut new_fields);
+
+    let mut changes = Vec::new();
+    for (path, old_value) in &old_fields {
+        match new_fields.get(path) {
+            None => changes.push(format!("- {}: {}", path, display(old_value))),
+            Some(new_value) if new_value != old_value => changes.push(format!(
+                "~ {}: {} -> {}",
+                path,
+                display(old_value),
+                display(new_value)
+            )),
+            Some(_) => {}
+        }
+    }
+    for (path, new_value) in new_fields
+        .iter()
+        .filter(|(path, _)| !old_fields.contains_key(*path))
+    {
+        changes.push(format!("+ {}: {}", path, display(new_value)));
+    }
+    Ok(changes)
+}
+
+pub fn diff(args: DiffConfigArgs) -> Result<()> {
+    let (_, old_config, old_runtime) = load(&args.old)?;
+    let (_, new_config, new_runtime) = load(&args.new)?;
+    let changes = changes(&old_config, &new_config)?;
+    for change in &changes {
+        println!("{}", change);
+    }
+
+    let (old_digest, new_digest) = (commitment_hex(&old_runtime), commitment_hex(&new_runtime));
+    if old_digest == new_digest {
+        println!("{} change(s); attested commitment unchanged", changes.len());
+    } else {
+        println!(
+            "{} change(s); attested commitment {} -> {}",
+            changes.len(),
+            old_digest,
+            new_digest
+        );
+    }
+    Ok(())
+}
+
+pub fn encrypt(args: EncryptConfigArgs) -> Result<()> {
+    let (raw, config, runtime) = load(&args.config)?;
+    for (path, message) in lint(&config) {
+        eprintln!("warning: {}: {}", path, message);
+    }
+    let seal_config: SealConfig = serde_yaml::from_str(&crate::read_input(&args.seal_config)?)
+        .map_err(|e| anyhow!("{}: invalid Seal config: {}", args.seal_config, e))?;
+    let id = hex::decode(args.id.trim_start_matches("0x")).context("--id must be hex")?;
+    let threshold = match args.threshold {
+        Some(threshold) => threshold,
+        None => u8::try_from(seal_config.key_servers.len())
+            .context("too many key servers for the default threshold")?,
+    };
+
+    let encrypted = encrypt_config(&seal_config, id, threshold, raw.as_bytes())?;
+    eprintln!(
+        "Encrypted for {} of {} key servers; attested commitment {}",
+        threshold,
+        seal_config.key_servers.len(),
+        commitment_hex(&runtime)
+    );
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "encrypted_objects": hex::encode(bcs::to_bytes(&vec![encrypted])?),
+        }))?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Edit applied to `clean()` by a table case.
+    type Edit = fn(&mut FileKycConfig);
+
+    const P1_KEY: &str = "0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad";
+
+    /// A config `lint` has nothing to say about.
+    fn clean() -> FileKycConfig {
+        let raw = format!(
+            "version: 1\n\
+             walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             sanctions_list:\n  \
+               version: sdn-1\n  \
+               format: csv\n  \
+               data: \"name,aliases,date_of_birth,nationality\\n\"\n\
+             providers:\n\
+             - provider_id: p1\n  \
+               public_key: \"{}\"\n\
+             - provider_id: p2\n  \
+               public_key: \"{}\"\n  \
+               webhook_url: https://hooks.example/kyc\n",
+            "ab".repeat(144),
+            P1_KEY,
+            P1_KEY
+        );
+        FileKycConfig::parse(&raw).unwrap()
+    }
+
+    #[test]
+    fn clean_config_has_no_warnings() {
+        assert_eq!(lint(&clean()), []);
+    }
+
+    #[test]
+    fn each_lint_rule_reports_its_path() {
+        let cases: [(&str, Edit); 6] = [
+            ("version", |config| config.version = 0),
+            ("enclave_measurement", |config| {
+                config.enclave_measurement = format!("0x{}", "00".repeat(144))
+            }),
+            ("walrus_aggregator_url", |config| {
+                config.walrus_aggregator_url = "http://aggregator.example".to_string()
+            }),
+            ("providers[0].allow_unsigned", |config| {
+                config.providers[0].allow_unsigned = true
+            }),
+            ("providers[1].webhook_url", |config| {
+                config.providers[1].webhook_url = Some("http://hooks.example/kyc".to_string())
+            }),
+            ("sanctions_list", |config| config.sanctions_list = None),
+        ];
+        for (path, edit) in cases {
+            let mut config = clean();
+            edit(&mut config);
+            let paths: Vec<String> = lint(&config).into_iter().map(|(path, _)| path).collect();
+            assert_eq!(paths, [path]);
+        }
+    }
+
+    #[test]
+    fn flatten_keys_lists_by_id() {
+        let mut out = BTreeMap::new();
+        let value = json!({
+            "providers": [{ "provider_id": "p1", "validity_ms": 5 }],
+            "predicates": [{ "name": "over_18", "years": 18 }],
+            "blocked_wallets": ["0x1", "0x2"],
+            "restricted_jurisdictions": [],
+        });
+        flatten("", &value, &mut out);
+        assert_eq!(
+            out.into_iter().collect::<Vec<_>>(),
+            [
+                ("blocked_wallets[0]".to_string(), json!("0x1")),
+                ("blocked_wallets[1]".to_string(), json!("0x2")),
+                ("predicates[over_18].name".to_string(), json!("over_18")),
+                ("predicates[over_18].years".to_string(), json!(18)),
+                ("providers[p1].provider_id".to_string(), json!("p1")),
+                ("providers[p1].validity_ms".to_string(), json!(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn changes_report_providers_by_id() {
+        let cases: [(Edit, &[&str]); 4] = [
+            (|_| {}, &[]),
+            // Reordering providers is not a change.
+            (|config| config.providers.reverse(), &[]),
+            (|config| config.providers[0].validity_ms = Some(5), &[
+                "~ providers[p1].validity_ms: null -> 5",
+            ]),
+            (
+                |config| {
+                    config.providers.remove(1);
+                    let mut added = config.providers[0].clone();
+                    added.provider_id = "p3".to_string();
+                    config.providers.push(added);
+                },
+                &[
+                    "- providers[p2].allow_unsigned: false",
+                    "- providers[p2].provider_id: \"p2\"",
+                    "- providers[p2].public_key: \"0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad\"",
+                    "- providers[p2].rate_limit: null",
+                    "- providers[p2].validity_ms: null",
+                    "- providers[p2].webhook_url: \"https://hooks.example/kyc\"",
+                    "+ providers[p3].allow_unsigned: false",
+                    "+ providers[p3].provider_id: \"p3\"",
+                    "+ providers[p3].public_key: \"0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad\"",
+                    "+ providers[p3].rate_limit: null",
+                    "+ providers[p3].validity_ms: null",
+                    "+ providers[p3].webhook_url: null",
+                ],
+            ),
+        ];
+        for (edit, expected) in cases {
+            let mut new = clean();
+            edit(&mut new);
+            assert_eq!(changes(&clean(), &new).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn long_values_are_shortened() {
+        let short = json!("a".repeat(MAX_DIFF_VALUE_LEN - 2));
+        assert_eq!(display(&short), short.to_string());
+        let long = display(&json!("a".repeat(MAX_DIFF_VALUE_LEN)));
+        assert!(long.ends_with(&format!("... ({} chars)", MAX_DIFF_VALUE_LEN + 2)));
+        assert!(long.starts_with(&format!("\"{}", "a".repeat(MAX_DIFF_VALUE_LEN - 1))));
+    }
+}
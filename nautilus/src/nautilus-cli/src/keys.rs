@@ -0,0 +1,95 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PrivateKey};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use nautilus_server::app::KycProviderConfig;
+use std::io::Write;
+
+#[derive(Args)]
+pub struct KeygenArgs {
+    /// `provider_id` the enclave will know this key by.
+    #[arg(long)]
+    provider_id: String,
+    /// File to write the hex encoded private key to; must not exist yet.
+    #[arg(long)]
+    out: String,
+}
+
+pub fn run(args: KeygenArgs) -> Result<()> {
+    let keypair = Ed25519KeyPair::generate(&mut rand::thread_rng());
+    let public_key = keypair.public().clone();
+    let mut file = create_private(&args.out)?;
+    writeln!(file, "{}", Hex::encode(keypair.private().as_bytes()))?;
+
+    let entry = KycProviderConfig {
+        provider_id: args.provider_id,
+        public_key: format!("0x{}", Hex::encode(public_key.as_bytes())),
+        allow_unsigned: false,
+        validity_ms: None,
+        webhook_url: None,
+        rate_limit: None,
+    };
+    eprintln!("Private key written to {}. Add to `providers`:", args.out);
+    print!("{}", serde_yaml::to_string(&vec![entry])?);
+    Ok(())
+}
+
+/// Reads a private key written by `keygen`.
+pub(crate) fn load_keypair(path: &str) -> Result<Ed25519KeyPair> {
+    let contents = crate::read_input(path)?;
+    let bytes = Hex::decode(contents.trim().trim_start_matches("0x"))
+        .map_err(|_| anyhow!("{} does not hold a hex private key", path))?;
+    let private = Ed25519PrivateKey::from_bytes(&bytes)
+        .map_err(|e| anyhow!("{} is not an Ed25519 private key: {}", path, e))?;
+    Ok(Ed25519KeyPair::from(private))
+}
+
+fn create_private(path: &str) -> Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .with_context(|| format!("failed to create {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keygen_output_loads_back() {
+        let path = std::env::temp_dir().join(format!("nautilus-cli-key-{}", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        run(KeygenArgs {
+            provider_id: "p1".to_string(),
+            out: path.clone(),
+        })
+        .unwrap();
+
+        let keypair = load_keypair(&path).unwrap();
+        assert_eq!(
+            Hex::decode(std::fs::read_to_string(&path).unwrap().trim()).unwrap(),
+            keypair.private().as_bytes()
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // An existing key is never overwritten.
+        assert!(run(KeygenArgs {
+            provider_id: "p1".to_string(),
+            out: path.clone(),
+        })
+        .is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
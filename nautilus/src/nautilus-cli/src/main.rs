@@ -0,0 +1,63 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+mod attestation;
+mod config;
+mod keys;
+mod payload;
+mod proof;
+
+/// Tooling for the Nautilus KYC enclave and its providers.
+#[derive(Parser)]
+#[command(name = "nautilus-cli", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Verify a Nitro attestation document against the AWS root certificate.
+    VerifyAttestation(attestation::VerifyAttestationArgs),
+    /// Verify an enclave-signed response offline against the enclave public key.
+    VerifyProof(proof::VerifyProofArgs),
+    /// Check a KYC config the way the enclave loads it, plus lint rules.
+    ValidateConfig(config::ValidateConfigArgs),
+    /// Show what changes between two KYC configs.
+    DiffConfig(config::DiffConfigArgs),
+    /// Seal-encrypt a KYC config for `/seal/complete_parameter_load`.
+    EncryptConfig(config::EncryptConfigArgs),
+    /// Generate a provider keypair and print its `providers` config entry.
+    Keygen(keys::KeygenArgs),
+    /// Print the `docHash` of a document.
+    HashDoc(payload::HashDocArgs),
+    /// Sign a KYC payload and optionally submit it to an enclave.
+    Sign(payload::SignArgs),
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::VerifyAttestation(args) => attestation::run(args),
+        Command::VerifyProof(args) => proof::run(args),
+        Command::ValidateConfig(args) => config::validate(args),
+        Command::DiffConfig(args) => config::diff(args),
+        Command::EncryptConfig(args) => config::encrypt(args),
+        Command::Keygen(args) => keys::run(args),
+        Command::HashDoc(args) => payload::hash_doc(args),
+        Command::Sign(args) => payload::sign(args),
+    }
+}
+
+/// Reads a file, or stdin when the path is `-`.
+pub(crate) fn read_input(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut buf = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut buf)?;
+        Ok(buf)
+    } else {
+        std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("failed to read {}: {}", path, e))
+    }
+}
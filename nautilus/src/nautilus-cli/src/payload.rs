@@ -0,0 +1,193 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::Signer;
+use nautilus_server::app::{canonical_message, doc_hash, normalize_country, KycRequestPayload};
+use nautilus_server::common::ProcessDataRequest;
+use serde_json::Value;
+
+#[derive(Args)]
+pub struct HashDocArgs {
+    /// Document as uploaded to Walrus.
+    document: String,
+}
+
+#[derive(Args)]
+pub struct SignArgs {
+    /// JSON `KycRequestPayload`, bare or wrapped in `{"payload": ...}`. Use `-`
+    /// for stdin.
+    payload: String,
+    /// Private key file written by `keygen`.
+    #[arg(long)]
+    key: String,
+    /// Document to compute `docHash` from, replacing the one in the payload.
+    #[arg(long)]
+    doc: Option<String>,
+    /// POST the signed request to `<URL>/process_data` and print the response.
+    #[arg(long, value_name = "URL")]
+    enclave_url: Option<String>,
+}
+
+pub fn hash_doc(args: HashDocArgs) -> Result<()> {
+    let document = std::fs::read(&args.document)
+        .with_context(|| format!("failed to read {}", args.document))?;
+    println!("{}", doc_hash(&document));
+    Ok(())
+}
+
+pub fn sign(args: SignArgs) -> Result<()> {
+    let document = args
+        .doc
+        .as_ref()
+        .map(|doc| std::fs::read(doc).with_context(|| format!("failed to read {}", doc)))
+        .transpose()?;
+    let keypair = crate::keys::load_keypair(&args.key)?;
+    let payload = sign_payload(
+        &crate::read_input(&args.payload)?,
+        &keypair,
+        document.as_deref(),
+    )?;
+
+    let request = ProcessDataRequest { payload };
+    let Some(url) = args.enclave_url else {
+        println!("{}", serde_json::to_string_pretty(&request)?);
+        return Ok(());
+    };
+
+    let response = reqwest::blocking::Client::new()
+        .post(format!("{}/process_data", url.trim_end_matches('/')))
+        .json(&request)
+        .send()
+        .map_err(|e| anyhow!("failed to reach {}: {}", url, e))?;
+    let status = response.status();
+    let body = response.text()?;
+    if !status.is_success() {
+        bail!("enclave returned {}: {}", status, body);
+    }
+    let body = serde_json::from_str::<Value>(&body)
+        .map(|json| serde_json::to_string_pretty(&json).expect("should not fail"))
+        .unwrap_or(body);
+    println!("{}", body);
+    Ok(())
+}
+
+/// Parses `raw` as a bare or wrapped `KycRequestPayload`, replaces `docHash` with
+/// the hash of `document` if given, and sets `providerSignature`.
+fn sign_payload(
+    raw: &str,
+    keypair: &Ed25519KeyPair,
+    document: Option<&[u8]>,
+) -> Result<KycRequestPayload> {
+    let mut value: Value = serde_json::from_str(raw).context("payload is not valid JSON")?;
+    if value.get("payload").is_some() {
+        value = value["payload"].take();
+    }
+    let mut payload: KycRequestPayload =
+        serde_json::from_value(value).context("payload is not a KycRequestPayload")?;
+
+    if let Some(document) = document {
+        payload.docHash = doc_hash(document);
+    }
+    if normalize_country(&payload.nationality).is_none() {
+        bail!(
+            "nationality {} is not a recognized country",
+            payload.nationality
+        );
+    }
+    let message = canonical_message(&payload);
+    eprintln!("Signing 0x{}", Hex::encode(&message));
+    payload.providerSignature = Some(Hex::encode(keypair.sign(&message)));
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::traits::{KeyPair, ToFromBytes};
+    use nautilus_server::app::{verify_provider_signature, FileKycConfig, KycRuntime};
+    use serde_json::json;
+
+    fn unsigned() -> Value {
+        json!({
+            "userWallet": "0x1",
+            "providerId": "p1",
+            "kycLevel": 2,
+            "blobId": "blob",
+            "docHash": "0xab",
+            "walrusBlobObject": null,
+            "nationality": "Germany",
+            "cardArtCid": "cid",
+            "providerSignature": null,
+            "fullName": "Jane Roe",
+        })
+    }
+
+    /// Runtime registering `keypair` as provider `p1`, as `keygen` prints it.
+    fn runtime(keypair: &Ed25519KeyPair) -> KycRuntime {
+        let raw = format!(
+            "walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             providers:\n\
+             - provider_id: p1\n  \
+               public_key: \"0x{}\"\n",
+            "00".repeat(144),
+            Hex::encode(keypair.public().as_bytes())
+        );
+        KycRuntime::try_from(FileKycConfig::parse(&raw).unwrap()).unwrap()
+    }
+
+    fn keypair() -> Ed25519KeyPair {
+        Ed25519KeyPair::generate(&mut rand::thread_rng())
+    }
+
+    #[test]
+    fn signatures_are_accepted_by_the_enclave() {
+        let keypair = keypair();
+        let runtime = runtime(&keypair);
+        for raw in [unsigned(), json!({ "payload": unsigned() })] {
+            let payload = sign_payload(&raw.to_string(), &keypair, None).unwrap();
+            verify_provider_signature(&runtime.providers[0], &payload).unwrap();
+        }
+    }
+
+    #[test]
+    fn signatures_cover_the_payload() {
+        let keypair = keypair();
+        let mut payload = sign_payload(&unsigned().to_string(), &keypair, None).unwrap();
+        payload.fullName = Some("John Doe".to_string());
+        assert!(verify_provider_signature(&runtime(&keypair).providers[0], &payload).is_err());
+
+        let payload = sign_payload(&unsigned().to_string(), &keypair, None).unwrap();
+        let other = runtime(&self::keypair());
+        assert!(verify_provider_signature(&other.providers[0], &payload).is_err());
+    }
+
+    #[test]
+    fn document_replaces_the_doc_hash() {
+        let keypair = keypair();
+        let payload =
+            sign_payload(&unsigned().to_string(), &keypair, Some(b"passport scan")).unwrap();
+        assert_eq!(payload.docHash, doc_hash(b"passport scan"));
+        verify_provider_signature(&runtime(&keypair).providers[0], &payload).unwrap();
+    }
+
+    #[test]
+    fn invalid_payloads_are_not_signed() {
+        let keypair = keypair();
+        let mut unknown_country = unsigned();
+        unknown_country["nationality"] = json!("Atlantis");
+        let mut unknown_field = unsigned();
+        unknown_field["isPep"] = json!(false);
+        for raw in [
+            "not json".to_string(),
+            unknown_country.to_string(),
+            unknown_field.to_string(),
+        ] {
+            assert!(sign_payload(&raw, &keypair, None).is_err(), "{}", raw);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use fastcrypto::ed25519::Ed25519PublicKey;
+use fastcrypto::traits::ToFromBytes;
+use nautilus_server::app::KycProof;
+use nautilus_server::common::{current_timestamp_ms, IntentScope, TimestampBounds};
+
+/// Tolerated clock skew for proofs timestamped slightly ahead of the verifier.
+const CLOCK_SKEW_MS: u64 = 60_000;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Scope {
+    ProcessData,
+    KeyRotation,
+    Revocation,
+    Denial,
+    CommittedData,
+}
+
+impl From<Scope> for IntentScope {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::ProcessData => IntentScope::ProcessData,
+            Scope::KeyRotation => IntentScope::KeyRotation,
+            Scope::Revocation => IntentScope::Revocation,
+            Scope::Denial => IntentScope::Denial,
+            Scope::CommittedData => IntentScope::CommittedData,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct VerifyProofArgs {
+    /// File holding the proof: the JSON returned by the enclave, or hex BCS of
+    /// the signed intent message followed by the signature. Use `-` for stdin.
+    input: String,
+    /// Hex encoded Ed25519 public key of the enclave.
+    #[arg(long)]
+    public_key: String,
+    /// Hex signature, when the BCS input holds only the intent message.
+    #[arg(long)]
+    signature: Option<String>,
+    /// Intent scope the proof must be signed under (defaults to the one it carries).
+    #[arg(long, value_enum)]
+    scope: Option<Scope>,
+    /// Reject proofs signed before this Unix time in milliseconds.
+    #[arg(long, conflicts_with = "max_age_secs")]
+    not_before_ms: Option<u64>,
+    /// Reject proofs signed after this Unix time in milliseconds (defaults to
+    /// now plus one minute of clock skew).
+    #[arg(long)]
+    not_after_ms: Option<u64>,
+    /// Reject proofs signed more than this many seconds ago.
+    #[arg(long)]
+    max_age_secs: Option<u64>,
+    /// Verify as of this Unix time in milliseconds instead of now.
+    #[arg(long)]
+    at_ms: Option<u64>,
+}
+
+pub fn run(args: VerifyProofArgs) -> Result<()> {
+    let proof = KycProof::parse(&crate::read_input(&args.input)?, args.signature.as_deref())?;
+    let public_key = hex::decode(args.public_key.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| Ed25519PublicKey::from_bytes(&bytes).ok())
+        .context("--public-key must be a hex Ed25519 public key")?;
+
+    let at_ms = args.at_ms.unwrap_or_else(current_timestamp_ms);
+    let bounds = TimestampBounds {
+        not_before_ms: args.not_before_ms.unwrap_or_else(|| {
+            args.max_age_secs
+                .map(|secs| at_ms.saturating_sub(secs.saturating_mul(1_000)))
+                .unwrap_or(0)
+        }),
+        not_after_ms: args
+            .not_after_ms
+            .unwrap_or_else(|| at_ms.saturating_add(CLOCK_SKEW_MS)),
+    };
+
+    proof.verify(&public_key, args.scope.map(Into::into), &bounds)?;
+    println!("{}", serde_json::to_string_pretty(&proof.to_json())?);
+    Ok(())
+}
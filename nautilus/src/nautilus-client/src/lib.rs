@@ -0,0 +1,387 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Typed client for the nautilus-server API. Every enclave-signed response is
+//! checked against a trusted enclave key, either pinned or taken from a verified
+//! attestation, before it is returned.
+
+use fastcrypto::ed25519::Ed25519PublicKey;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::ToFromBytes;
+use nautilus_server::app::{
+    verify_jwt_vc, verify_opening, verify_sd_jwt, CompleteParameterLoadRequest,
+    CompleteParameterLoadResponse, CredentialFormat, InitParameterLoadRequest,
+    InitParameterLoadResponse, KycCredential, KycDenialPayload, KycRequestPayload,
+    RuntimeCommitment,
+};
+use nautilus_server::common::{
+    current_timestamp_ms, verify_signed_response, GetAttestationResponse, HealthCheckResponse,
+    IntentMessage, IntentScope, ProcessDataRequest, ProcessedDataResponse, ProofError,
+    TimestampBounds,
+};
+use nautilus_server::ErrorResponse;
+use nitro_verifier::{verify_attestation, VerifiedAttestation, VerifyOptions};
+use rand::RngCore;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Length of the random nonce sent with every attestation request.
+const ATTESTATION_NONCE_LEN: usize = 32;
+/// PCRs an attested client must pin: the enclave image, kernel and application.
+const REQUIRED_PCRS: [u64; 3] = [0, 1, 2];
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClientError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("enclave returned {status}: {}", .body.error)]
+    Api {
+        status: StatusCode,
+        body: ErrorResponse,
+    },
+    /// Request refused by policy; `denial` carries a verified enclave signature.
+    #[error("enclave denied the request: {message}")]
+    Denied {
+        message: String,
+        denial: Box<KycDenialPayload>,
+    },
+    /// Request exceeded a rate limit; `retry_after` is the enclave's Retry-After.
+    #[error("enclave rate limited the request: {message}")]
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    #[error("invalid enclave signature: {0}")]
+    Proof(#[from] ProofError),
+    #[error("enclave reports public key {0}, which is not the trusted key")]
+    UntrustedKey(String),
+    #[error("invalid attestation: {0}")]
+    Attestation(#[from] nitro_verifier::VerifyError),
+    #[error("attestation does not satisfy the policy: {0}")]
+    AttestationPolicy(String),
+    #[error("invalid credential: {0}")]
+    InvalidCredential(String),
+    #[error("no host URL configured for host-only endpoints")]
+    NoHostUrl,
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// What an attestation must show before its public key is trusted.
+#[derive(Debug, Clone, Default)]
+pub struct AttestationPolicy {
+    /// PCR index to expected value, e.g. PCR0/1/2 from the reproducible build.
+    /// `NautilusClient::attested` requires at least PCR0, PCR1 and PCR2.
+    pub expected_pcrs: BTreeMap<u64, Vec<u8>>,
+    /// PEM certificate to trust instead of the bundled AWS Nitro root.
+    pub root_pem: Option<String>,
+    /// Reject attestation documents older than this.
+    pub max_age_ms: Option<u64>,
+}
+
+/// A verified `/get_attestation` response.
+#[derive(Debug, Clone)]
+pub struct AttestedEnclave {
+    pub attestation: VerifiedAttestation,
+    pub public_key: Ed25519PublicKey,
+    /// Loaded runtime config; its digest matched the attestation `user_data`.
+    pub commitment: Option<RuntimeCommitment>,
+}
+
+pub struct NautilusClient {
+    http: reqwest::Client,
+    base_url: String,
+    host_url: Option<String>,
+    policy: AttestationPolicy,
+    /// Whether `enclave_key` follows the latest attestation or is fixed.
+    attested: bool,
+    enclave_key: RwLock<Ed25519PublicKey>,
+}
+
+impl NautilusClient {
+    /// Trusts `enclave_key`, obtained out of band (e.g. from the on-chain
+    /// enclave object). Attestations are still checked against the AWS root.
+    pub fn pinned(base_url: impl Into<String>, enclave_key: Ed25519PublicKey) -> Self {
+        NautilusClient {
+            http: reqwest::Client::new(),
+            base_url: trim_url(base_url.into()),
+            host_url: None,
+            policy: AttestationPolicy::default(),
+            attested: false,
+            enclave_key: RwLock::new(enclave_key),
+        }
+    }
+
+    /// Fetches and verifies a fresh attestation and trusts the key it commits
+    /// to. Later calls to `get_attestation` pick up rotated keys. The policy must
+    /// pin PCR0, PCR1 and PCR2, or any enclave image would be trusted.
+    pub async fn attested(base_url: impl Into<String>, policy: AttestationPolicy) -> Result<Self> {
+        if let Some(index) = REQUIRED_PCRS
+            .iter()
+            .find(|index| !policy.expected_pcrs.contains_key(index))
+        {
+            return Err(ClientError::AttestationPolicy(format!(
+                "expected_pcrs must include PCR{}",
+                index
+            )));
+        }
+        let http = reqwest::Client::new();
+        let base_url = trim_url(base_url.into());
+        let attested = fetch_attestation(&http, &base_url, &policy).await?;
+        Ok(NautilusClient {
+            http,
+            base_url,
+            host_url: None,
+            policy,
+            attested: true,
+            enclave_key: RwLock::new(attested.public_key),
+        })
+    }
+
+    /// Base URL of the host-only server (port 3001), for the Seal bootstrap.
+    pub fn with_host_url(mut self, host_url: impl Into<String>) -> Self {
+        self.host_url = Some(trim_url(host_url.into()));
+        self
+    }
+
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// The key responses are currently verified against.
+    pub fn enclave_public_key(&self) -> Ed25519PublicKey {
+        self.enclave_key.read().expect("lock poisoned").clone()
+    }
+
+    /// Submits a KYC payload and verifies the credential in `format` against
+    /// the trusted key, including any commitment openings.
+    pub async fn process_data(
+        &self,
+        payload: &KycRequestPayload,
+        format: CredentialFormat,
+    ) -> Result<KycCredential> {
+        let request = self
+            .http
+            .post(format!("{}/process_data", self.base_url))
+            .query(&[("format", format.as_str())])
+            .json(&ProcessDataRequest { payload });
+        let credential: KycCredential = self.send(request).await?;
+        self.verify_credential(&credential)?;
+        Ok(credential)
+    }
+
+    /// Requests an attestation bound to a random nonce and verifies it. With an
+    /// attested client the attested key becomes the trusted key; a pinned client
+    /// requires it to equal the pinned key.
+    pub async fn get_attestation(&self) -> Result<AttestedEnclave> {
+        let attested = fetch_attestation(&self.http, &self.base_url, &self.policy).await?;
+        if self.attested {
+            *self.enclave_key.write().expect("lock poisoned") = attested.public_key.clone();
+        } else {
+            self.check_key(&attested.public_key)?;
+        }
+        Ok(attested)
+    }
+
+    /// Health check; fails if the enclave reports a key other than the trusted one.
+    pub async fn health_check(&self) -> Result<HealthCheckResponse> {
+        let response: HealthCheckResponse = self
+            .send(self.http.get(format!("{}/health_check", self.base_url)))
+            .await?;
+        let key = Hex::decode(&response.pk)
+            .ok()
+            .and_then(|bytes| Ed25519PublicKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| ClientError::UntrustedKey(response.pk.clone()))?;
+        self.check_key(&key)?;
+        Ok(response)
+    }
+
+    /// First step of the Seal config bootstrap. The response is not signed by
+    /// the enclave; the Seal key servers check the request it carries.
+    pub async fn init_parameter_load(
+        &self,
+        request: &InitParameterLoadRequest,
+    ) -> Result<InitParameterLoadResponse> {
+        let url = format!("{}/seal/init_parameter_load", self.host_url()?);
+        self.send(self.http.post(url).json(request)).await
+    }
+
+    /// Second step of the Seal config bootstrap. Confirm the loaded config
+    /// afterwards with `get_attestation`, whose commitment covers it.
+    pub async fn complete_parameter_load(
+        &self,
+        request: &CompleteParameterLoadRequest,
+    ) -> Result<CompleteParameterLoadResponse> {
+        let url = format!("{}/seal/complete_parameter_load", self.host_url()?);
+        self.send(self.http.post(url).json(request)).await
+    }
+
+    fn host_url(&self) -> Result<&str> {
+        self.host_url.as_deref().ok_or(ClientError::NoHostUrl)
+    }
+
+    fn check_key(&self, key: &Ed25519PublicKey) -> Result<()> {
+        if *key == self.enclave_public_key() {
+            Ok(())
+        } else {
+            Err(ClientError::UntrustedKey(Hex::encode(key.as_bytes())))
+        }
+    }
+
+    /// Sends the request and decodes the body, turning error responses into
+    /// `ClientError`s. Signed denials are verified before being reported.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json().await?);
+        }
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs);
+        let text = response.text().await?;
+        let body = serde_json::from_str::<ErrorResponse>(&text).unwrap_or(ErrorResponse {
+            error: text,
+            violations: Vec::new(),
+            denial: None,
+            retry_after_secs: None,
+        });
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(ClientError::RateLimited {
+                message: body.error,
+                retry_after,
+            });
+        }
+        let Some(denial) = body.denial.clone() else {
+            return Err(ClientError::Api { status, body });
+        };
+        let signed: ProcessedDataResponse<IntentMessage<KycDenialPayload>> =
+            serde_json::from_value(denial)
+                .map_err(|e| ClientError::InvalidCredential(format!("denial: {}", e)))?;
+        self.verify_signed(&signed, IntentScope::Denial)?;
+        Err(ClientError::Denied {
+            message: body.error,
+            denial: Box::new(signed.response.data),
+        })
+    }
+
+    /// Checks the signature over the BCS bytes `to_signed_response` signs.
+    fn verify_signed<T: Serialize>(
+        &self,
+        signed: &ProcessedDataResponse<IntentMessage<T>>,
+        scope: IntentScope,
+    ) -> Result<()> {
+        let key = self.enclave_public_key();
+        Ok(verify_signed_response(
+            &key,
+            signed,
+            scope,
+            &TimestampBounds::unbounded(),
+        )?)
+    }
+
+    fn verify_credential(&self, credential: &KycCredential) -> Result<()> {
+        let key = self.enclave_public_key();
+        match credential {
+            KycCredential::Plain(signed) => self.verify_signed(signed, IntentScope::ProcessData),
+            KycCredential::Committed(committed) => {
+                self.verify_signed(&committed.signed, IntentScope::CommittedData)?;
+                let commitments = &committed.signed.response.data.commitments;
+                for opening in &committed.openings {
+                    if !commitments
+                        .iter()
+                        .any(|commitment| verify_opening(commitment, opening))
+                    {
+                        return Err(ClientError::InvalidCredential(format!(
+                            "opening of {} does not match its commitment",
+                            opening.name
+                        )));
+                    }
+                }
+                Ok(())
+            }
+            KycCredential::JwtVc(vc) => verify_jwt_vc(&vc.jwt_vc, &key, now_secs())
+                .map(|_| ())
+                .map_err(|e| ClientError::InvalidCredential(e.to_string())),
+            KycCredential::SdJwt(sd) => verify_sd_jwt(&sd.sd_jwt, &key, None, now_secs())
+                .map(|_| ())
+                .map_err(|e| ClientError::InvalidCredential(e.to_string())),
+        }
+    }
+}
+
+/// Fetches `/get_attestation` with a fresh nonce and checks the document chain,
+/// nonce and PCRs, and that `user_data` is the digest of the returned commitment.
+async fn fetch_attestation(
+    http: &reqwest::Client,
+    base_url: &str,
+    policy: &AttestationPolicy,
+) -> Result<AttestedEnclave> {
+    let mut nonce = [0u8; ATTESTATION_NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let response = http
+        .get(format!("{}/get_attestation", base_url))
+        .query(&[("nonce", Hex::encode(nonce))])
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.json::<ErrorResponse>().await?;
+        return Err(ClientError::Api { status, body });
+    }
+    let response: GetAttestationResponse = response.json().await?;
+
+    let document = Hex::decode(&response.attestation)
+        .map_err(|e| ClientError::AttestationPolicy(format!("document is not hex: {}", e)))?;
+    let mut options = VerifyOptions::new(now_secs() * 1000);
+    options.max_age_ms = policy.max_age_ms;
+    options.expected_nonce = Some(nonce.to_vec());
+    if let Some(root) = &policy.root_pem {
+        options = options.with_root_pem(root)?;
+    }
+    let attestation = verify_attestation(&document, &options)?;
+
+    for (index, expected) in &policy.expected_pcrs {
+        if attestation.pcr(*index) != Some(expected.as_slice()) {
+            return Err(ClientError::AttestationPolicy(format!(
+                "PCR{} does not match",
+                index
+            )));
+        }
+    }
+    let public_key = attestation
+        .public_key
+        .as_deref()
+        .and_then(|bytes| Ed25519PublicKey::from_bytes(bytes).ok())
+        .ok_or_else(|| ClientError::AttestationPolicy("no Ed25519 public key".to_string()))?;
+    let digest = response
+        .commitment
+        .as_ref()
+        .map(|commitment| commitment.digest().to_vec());
+    if attestation.user_data != digest {
+        return Err(ClientError::AttestationPolicy(
+            "user_data does not commit to the returned config".to_string(),
+        ));
+    }
+    Ok(AttestedEnclave {
+        attestation,
+        public_key,
+        commitment: response.commitment,
+    })
+}
+
+fn trim_url(url: String) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+fn now_secs() -> u64 {
+    current_timestamp_ms() / 1000
+}
@@ -0,0 +1,229 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use axum::routing::post;
+use axum::{Json, Router};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::KeyPair;
+use nautilus_client::{AttestationPolicy, ClientError, NautilusClient};
+use nautilus_server::app::{
+    field_commitment, CommittedKycResponse, CredentialFormat, FieldCommitment, FieldOpening,
+    KycCommittedResponsePayload, KycCredential, KycRequestPayload, KycResponsePayload,
+};
+use nautilus_server::common::{to_signed_response, IntentScope, ProofError};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const SALT: [u8; 32] = [9; 32];
+
+fn keypair() -> Ed25519KeyPair {
+    Ed25519KeyPair::generate(&mut rand::thread_rng())
+}
+
+fn request() -> KycRequestPayload {
+    serde_json::from_value(json!({
+        "userWallet": "0x1",
+        "providerId": "p1",
+        "kycLevel": 2,
+        "blobId": "blob",
+        "docHash": "0xab",
+        "walrusBlobObject": null,
+        "nationality": "DE",
+        "cardArtCid": "cid",
+        "providerSignature": null,
+    }))
+    .unwrap()
+}
+
+fn response() -> KycResponsePayload {
+    serde_json::from_value(json!({
+        "userWallet": "0x1",
+        "providerId": "p1",
+        "kycLevel": 2,
+        "isPep": false,
+        "isSanctioned": false,
+        "blobId": "blob",
+        "docHash": "0xab",
+        "walrusCid": "blob",
+        "teeMeasurement": "0x00",
+        "nationality": "DE",
+        "cardArtCid": "cid",
+        "issuedAtMs": 0,
+        "expiresAtMs": 1,
+        "sanctionsListVersion": null,
+        "pepMatchConfidence": 0,
+        "pepListVersion": null,
+        "predicates": [],
+    }))
+    .unwrap()
+}
+
+fn committed(kp: &Ed25519KeyPair, opened_value: &str) -> CommittedKycResponse {
+    let payload = KycCommittedResponsePayload {
+        userWallet: "0x1".to_string(),
+        providerId: "p1".to_string(),
+        kycLevel: 2,
+        blobId: "blob".to_string(),
+        docHash: "0xab".to_string(),
+        walrusCid: "blob".to_string(),
+        teeMeasurement: "0x00".to_string(),
+        cardArtCid: "cid".to_string(),
+        issuedAtMs: 0,
+        expiresAtMs: 1,
+        sanctionsListVersion: None,
+        pepListVersion: None,
+        predicates: Vec::new(),
+        disclosed: Vec::new(),
+        commitments: vec![FieldCommitment {
+            name: "isSanctioned".to_string(),
+            commitment: field_commitment("isSanctioned", "false", &SALT).to_vec(),
+        }],
+    };
+    CommittedKycResponse {
+        signed: to_signed_response(kp, payload, 0, IntentScope::CommittedData),
+        openings: vec![FieldOpening {
+            name: "isSanctioned".to_string(),
+            value: opened_value.to_string(),
+            salt: Hex::encode(SALT),
+        }],
+    }
+}
+
+/// Serves `body` from `/process_data` on a local port and returns the base URL.
+async fn mock_enclave(body: Value) -> String {
+    let app = Router::new().route("/process_data", post(move || async move { Json(body) }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+async fn process_data(kp: &Ed25519KeyPair, body: Value) -> Result<KycCredential, ClientError> {
+    let client = NautilusClient::pinned(mock_enclave(body).await, kp.public().clone());
+    client
+        .process_data(&request(), CredentialFormat::Nautilus)
+        .await
+}
+
+#[tokio::test]
+async fn signed_credentials_are_accepted() {
+    let kp = keypair();
+    let signed = to_signed_response(&kp, response(), 0, IntentScope::ProcessData);
+    let credential = process_data(&kp, json!(signed)).await.unwrap();
+    assert!(matches!(credential, KycCredential::Plain(_)));
+
+    let credential = process_data(&kp, json!(committed(&kp, "false")))
+        .await
+        .unwrap();
+    assert!(matches!(credential, KycCredential::Committed(_)));
+}
+
+#[tokio::test]
+async fn tampered_signed_payload_is_rejected() {
+    let kp = keypair();
+    let mut body = json!(to_signed_response(
+        &kp,
+        response(),
+        0,
+        IntentScope::ProcessData
+    ));
+    body["response"]["data"]["isSanctioned"] = json!(true);
+    assert!(matches!(
+        process_data(&kp, body).await,
+        Err(ClientError::Proof(ProofError::InvalidSignature))
+    ));
+}
+
+#[tokio::test]
+async fn response_signed_by_another_key_is_rejected() {
+    let signed = to_signed_response(&keypair(), response(), 0, IntentScope::ProcessData);
+    assert!(matches!(
+        process_data(&keypair(), json!(signed)).await,
+        Err(ClientError::Proof(ProofError::InvalidSignature))
+    ));
+}
+
+#[tokio::test]
+async fn tampered_opening_is_rejected() {
+    let kp = keypair();
+    assert!(matches!(
+        process_data(&kp, json!(committed(&kp, "true"))).await,
+        Err(ClientError::InvalidCredential(_))
+    ));
+}
+
+#[tokio::test]
+async fn attested_client_requires_pinned_pcrs() {
+    // Fails before any request is made, so the URL is never contacted.
+    let result = NautilusClient::attested("http://127.0.0.1:9", AttestationPolicy::default()).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::AttestationPolicy(message)) if message.contains("PCR0")
+    ));
+
+    let policy = AttestationPolicy {
+        expected_pcrs: BTreeMap::from([(0, vec![0; 48]), (1, vec![0; 48])]),
+        ..Default::default()
+    };
+    assert!(matches!(
+        NautilusClient::attested("http://127.0.0.1:9", policy).await,
+        Err(ClientError::AttestationPolicy(message)) if message.contains("PCR2")
+    ));
+}
+
+/// Serves the real `/get_attestation` handler backed by the software dev
+/// attestor and returns the base URL.
+#[cfg(feature = "dev-attestor")]
+async fn dev_enclave() -> String {
+    use axum::routing::get;
+    use nautilus_server::common::{get_attestation, DevAttestor, EnclaveKey, KeyRing};
+    use nautilus_server::AppState;
+    use std::sync::Arc;
+
+    let state = Arc::new(AppState {
+        keys: tokio::sync::RwLock::new(KeyRing::new(EnclaveKey::generate(0))),
+        attestor: Box::new(DevAttestor::new()),
+        measurement: None,
+        walrus_client: reqwest::Client::new(),
+        kyc_runtime: Default::default(),
+        kyc_jobs: Default::default(),
+        rate_limiter: Default::default(),
+    });
+    let app = Router::new()
+        .route("/get_attestation", get(get_attestation))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+#[cfg(feature = "dev-attestor")]
+#[tokio::test]
+async fn dev_attestations_are_never_trusted() {
+    use nautilus_server::common::{Attestor, DevAttestor};
+
+    let url = dev_enclave().await;
+    let result = NautilusClient::attested(url.as_str(), AttestationPolicy::default()).await;
+    assert!(matches!(result, Err(ClientError::AttestationPolicy(_))));
+
+    // Pinning the all-zero PCRs the dev attestor reports does not help: the
+    // document is EdDSA signed with no AWS certificate chain, which the verifier
+    // refuses whatever the policy.
+    let zero = DevAttestor::new().measurement().unwrap();
+    let policy = AttestationPolicy {
+        expected_pcrs: BTreeMap::from([(0, zero.pcr0), (1, zero.pcr1), (2, zero.pcr2)]),
+        ..Default::default()
+    };
+    let result = NautilusClient::attested(url.as_str(), policy).await;
+    assert!(
+        matches!(
+            result,
+            Err(ClientError::Attestation(nitro_verifier::VerifyError::InvalidCose(ref message)))
+                if message.contains("unsupported algorithm")
+        ),
+        "{:?}",
+        result.err()
+    );
+}
@@ -0,0 +1,361 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::validation::INVALID_COUNTRY;
+use super::{ensure_runtime, normalize_country, KycRequestPayload, KycRuntime, ProviderRuntime};
+use crate::common::{current_timestamp_ms, to_signed_response, IntentScope};
+use crate::AppState;
+use crate::{EnclaveError, ErrorResponse, FieldViolation};
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::collections::HashSet;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Why the enclave refused to issue a KYC result.
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[repr(u8)]
+pub enum DenialReason {
+    BlockedWallet = 1,
+    RestrictedJurisdiction = 2,
+}
+
+/// Refusal signed by the enclave under `IntentScope::Denial`, returned alongside
+/// the error so the rejection can be audited later.
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct KycDenialPayload {
+    pub userWallet: String,
+    pub providerId: String,
+    pub blobId: String,
+    pub reasonCode: u8,
+    pub teeMeasurement: String,
+}
+
+/// Normalizes a Sui address to `0x` followed by 64 lowercase hex digits, padding
+/// short forms such as `0x2`. Returns None if `address` is not an address.
+pub(crate) fn normalize_sui_address(address: &str) -> Option<String> {
+    let trimmed = address.trim();
+    let hex = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))?;
+    if hex.is_empty() || hex.len() > 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("0x{:0>64}", hex.to_lowercase()))
+}
+
+impl KycRuntime {
+    pub fn is_wallet_blocked(&self, wallet: &str) -> bool {
+        normalize_sui_address(wallet).is_some_and(|wallet| self.blocked_wallets.contains(&wallet))
+    }
+}
+
+/// Applies the issuance policy to an authorized payload: normalizes `nationality`
+/// to ISO 3166-1 alpha-2 and refuses blocked wallets and restricted jurisdictions
+/// with an enclave-signed denial. Returns the normalized nationality.
+pub(crate) async fn check_policy(
+    state: &AppState,
+    runtime: &KycRuntime,
+    provider: &ProviderRuntime,
+    payload: &KycRequestPayload,
+) -> Result<&'static str, EnclaveError> {
+    // Blocked wallets are denied before anything else in the payload is parsed.
+    let (reason, message) = if runtime.is_wallet_blocked(&payload.userWallet) {
+        (
+            DenialReason::BlockedWallet,
+            format!("Wallet {} is blocked", payload.userWallet),
+        )
+    } else {
+        let nationality = normalize_country(&payload.nationality).ok_or_else(|| {
+            EnclaveError::InvalidRequest(vec![FieldViolation::new("nationality", INVALID_COUNTRY)])
+        })?;
+        if !runtime.restricted_jurisdictions.contains(nationality) {
+            return Ok(nationality);
+        }
+        (
+            DenialReason::RestrictedJurisdiction,
+            format!("Nationality {} is a restricted jurisdiction", nationality),
+        )
+    };
+
+    let denial = KycDenialPayload {
+        userWallet: payload.userWallet.clone(),
+        providerId: provider.provider_id.clone(),
+        blobId: payload.blobId.clone(),
+        reasonCode: reason as u8,
+        teeMeasurement: runtime.enclave_measurement.clone(),
+    };
+    let signing_key = state.signing_key().await;
+    let signed = to_signed_response(
+        &signing_key.keypair,
+        denial,
+        current_timestamp_ms(),
+        IntentScope::Denial,
+    );
+    Err(EnclaveError::Denied {
+        message,
+        denial: serde_json::to_value(signed).expect("should not fail"),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlockWalletsRequest {
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlockWalletsResponse {
+    pub blocked_wallet_count: usize,
+}
+
+/// Host-only endpoint adding addresses to the blocked set of the loaded runtime.
+/// Host changes last until a new config is loaded, which resets the set to the
+/// config list.
+#[utoipa::path(
+    post,
+    path = "/block_wallets",
+    tag = "host",
+    request_body = BlockWalletsRequest,
+    responses(
+        (status = 200, body = BlockWalletsResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn block_wallets(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BlockWalletsRequest>,
+) -> Result<Json<BlockWalletsResponse>, EnclaveError> {
+    update_blocked_wallets(&state, &request, |blocked, addresses| {
+        blocked.extend(addresses)
+    })
+    .await
+}
+
+/// Host-only endpoint removing addresses from the blocked set of the loaded
+/// runtime, including ones listed in the config. Unknown addresses are ignored.
+#[utoipa::path(
+    post,
+    path = "/unblock_wallets",
+    tag = "host",
+    request_body = BlockWalletsRequest,
+    responses(
+        (status = 200, body = BlockWalletsResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn unblock_wallets(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BlockWalletsRequest>,
+) -> Result<Json<BlockWalletsResponse>, EnclaveError> {
+    update_blocked_wallets(&state, &request, |blocked, addresses| {
+        for address in addresses {
+            blocked.remove(&address);
+        }
+    })
+    .await
+}
+
+/// Normalizes `request.addresses`, refusing the whole request if any is invalid,
+/// and applies `update` to the blocked set of the loaded runtime.
+async fn update_blocked_wallets(
+    state: &AppState,
+    request: &BlockWalletsRequest,
+    update: impl FnOnce(&mut HashSet<String>, Vec<String>),
+) -> Result<Json<BlockWalletsResponse>, EnclaveError> {
+    let addresses = request
+        .addresses
+        .iter()
+        .map(|address| {
+            normalize_sui_address(address).ok_or_else(|| {
+                EnclaveError::GenericError(format!("Invalid Sui address {}", address))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    ensure_runtime(state).await?;
+    let mut guard = state.kyc_runtime.write().await;
+    let runtime = guard
+        .as_mut()
+        .ok_or_else(|| EnclaveError::GenericError("KYC config not loaded".to_string()))?;
+    update(Arc::make_mut(&mut runtime.blocked_wallets), addresses);
+
+    Ok(Json(BlockWalletsResponse {
+        blocked_wallet_count: runtime.blocked_wallets.len(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::FileKycConfig;
+    use crate::common::{verify_signed_response, EnclaveKey, KeyRing, NitroAttestor};
+    use crate::common::{IntentMessage, ProcessedDataResponse, TimestampBounds};
+    use fastcrypto::traits::KeyPair;
+    use reqwest::Client;
+    use serde_json::json;
+    use tokio::sync::RwLock;
+
+    const WALLET: &str = "0x00000000000000000000000000000000000000000000000000000000000000ab";
+
+    fn state() -> Arc<AppState> {
+        let raw = format!(
+            "walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             blocked_wallets: [\"0xAB\"]\n\
+             restricted_jurisdictions: [Cuba]\n\
+             providers:\n\
+             - provider_id: p1\n  \
+               public_key: \"0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad\"\n",
+            "00".repeat(144)
+        );
+        let runtime = KycRuntime::try_from(FileKycConfig::parse(&raw).unwrap()).unwrap();
+        Arc::new(AppState {
+            keys: RwLock::new(KeyRing::new(EnclaveKey::generate(0))),
+            attestor: Box::new(NitroAttestor),
+            measurement: None,
+            walrus_client: Client::new(),
+            kyc_runtime: RwLock::new(Some(runtime)),
+            kyc_jobs: Default::default(),
+            rate_limiter: Default::default(),
+        })
+    }
+
+    fn payload(wallet: &str, nationality: &str) -> KycRequestPayload {
+        serde_json::from_value(json!({
+            "userWallet": wallet,
+            "providerId": "p1",
+            "kycLevel": 2,
+            "blobId": "blob",
+            "docHash": "0xab",
+            "walrusBlobObject": null,
+            "nationality": nationality,
+            "cardArtCid": "cid",
+            "providerSignature": null,
+        }))
+        .unwrap()
+    }
+
+    async fn policy(
+        state: &AppState,
+        payload: &KycRequestPayload,
+    ) -> Result<&'static str, EnclaveError> {
+        let guard = state.kyc_runtime.read().await;
+        let runtime = guard.as_ref().unwrap();
+        check_policy(state, runtime, &runtime.providers[0], payload).await
+    }
+
+    /// Checks the denial is signed by the current key and returns its payload.
+    async fn denial(state: &AppState, error: EnclaveError) -> KycDenialPayload {
+        let EnclaveError::Denied { denial, .. } = error else {
+            panic!("expected a denial, got {}", error);
+        };
+        let signed: ProcessedDataResponse<IntentMessage<KycDenialPayload>> =
+            serde_json::from_value(denial).unwrap();
+        let key = state.signing_key().await;
+        verify_signed_response(
+            key.keypair.public(),
+            &signed,
+            IntentScope::Denial,
+            &TimestampBounds::unbounded(),
+        )
+        .unwrap();
+        signed.response.data
+    }
+
+    #[test]
+    fn sui_addresses_are_normalized() {
+        assert_eq!(normalize_sui_address("0xab").as_deref(), Some(WALLET));
+        assert_eq!(normalize_sui_address(" 0XAB ").as_deref(), Some(WALLET));
+        assert_eq!(normalize_sui_address(WALLET).as_deref(), Some(WALLET));
+        let longest = format!("0x{}", "f".repeat(64));
+        assert_eq!(normalize_sui_address(&longest), Some(longest));
+        for invalid in [
+            "",
+            "0x",
+            "ab",
+            "0xzz",
+            "alice",
+            format!("0x{}", "1".repeat(65)).as_str(),
+        ] {
+            assert_eq!(normalize_sui_address(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn allowed_payloads_get_the_normalized_nationality() {
+        let state = state();
+        assert_eq!(
+            policy(&state, &payload("0x1", "Germany")).await.unwrap(),
+            "DE"
+        );
+        let Err(EnclaveError::InvalidRequest(violations)) =
+            policy(&state, &payload("0x1", "Atlantis")).await
+        else {
+            panic!("an unknown nationality should be rejected");
+        };
+        assert_eq!(violations[0].field, "nationality");
+    }
+
+    #[tokio::test]
+    async fn blocked_wallets_are_denied() {
+        let state = state();
+        // The wallet is blocked whatever its spelling, and before the
+        // nationality is looked at.
+        let error = policy(&state, &payload("0x00ab", "Atlantis"))
+            .await
+            .unwrap_err();
+        let denial = denial(&state, error).await;
+        assert_eq!(denial.reasonCode, DenialReason::BlockedWallet as u8);
+        assert_eq!(denial.userWallet, "0x00ab");
+        assert_eq!(denial.providerId, "p1");
+        assert_eq!(denial.blobId, "blob");
+        assert_eq!(denial.teeMeasurement, format!("0x{}", "00".repeat(144)));
+    }
+
+    #[tokio::test]
+    async fn restricted_jurisdictions_are_denied() {
+        let state = state();
+        let error = policy(&state, &payload("0x1", "cu")).await.unwrap_err();
+        assert!(error.to_string().contains("CU"), "{}", error);
+        let denial = denial(&state, error).await;
+        assert_eq!(
+            denial.reasonCode,
+            DenialReason::RestrictedJurisdiction as u8
+        );
+    }
+
+    #[tokio::test]
+    async fn wallets_can_be_blocked_and_unblocked() {
+        let state = state();
+        let request = |addresses: &[&str]| {
+            Json(BlockWalletsRequest {
+                addresses: addresses.iter().map(|a| a.to_string()).collect(),
+            })
+        };
+        let Json(added) = block_wallets(State(state.clone()), request(&["0x1", "0X01"]))
+            .await
+            .unwrap();
+        assert_eq!(added.blocked_wallet_count, 2);
+        assert!(policy(&state, &payload("0x1", "DE")).await.is_err());
+
+        // Addresses from the config can be unblocked too.
+        let Json(removed) = unblock_wallets(State(state.clone()), request(&["0x1", WALLET, "0x2"]))
+            .await
+            .unwrap();
+        assert_eq!(removed.blocked_wallet_count, 0);
+        assert!(policy(&state, &payload("0x1", "DE")).await.is_ok());
+        assert!(policy(&state, &payload(WALLET, "DE")).await.is_ok());
+
+        // One invalid address rejects the whole request.
+        assert!(
+            block_wallets(State(state.clone()), request(&["0x3", "nope"]))
+                .await
+                .is_err()
+        );
+        let guard = state.kyc_runtime.read().await;
+        assert!(guard.as_ref().unwrap().blocked_wallets.is_empty());
+    }
+}
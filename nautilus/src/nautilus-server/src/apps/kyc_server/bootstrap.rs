@@ -2,8 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::types::*;
+use crate::common::{current_timestamp_ms, rotate_key};
 use crate::AppState;
-use crate::EnclaveError;
+use crate::{EnclaveError, ErrorResponse};
 use axum::extract::State;
 use axum::routing::{get, post};
 use axum::Json;
@@ -14,10 +15,10 @@ use fastcrypto::traits::{KeyPair, Signer};
 use rand::thread_rng;
 use seal_sdk::types::{FetchKeyRequest, KeyId};
 use seal_sdk::{
-    genkey, seal_decrypt_all_objects, signed_message, signed_request, Certificate, ElGamalSecretKey,
+    genkey, seal_decrypt_all_objects, seal_encrypt, signed_message, signed_request, Certificate,
+    ElGamalSecretKey, EncryptedObject, EncryptionInput, IBEPublicKeys,
 };
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 use sui_sdk_types::{
     Argument, Command, Identifier, Input, MoveCall, ObjectId as ObjectID, PersonalMessage,
     ProgrammableTransaction,
@@ -74,6 +75,16 @@ async fn create_ptb(
     Ok(ProgrammableTransaction { inputs, commands })
 }
 
+#[utoipa::path(
+    post,
+    path = "/seal/init_parameter_load",
+    tag = "host",
+    request_body = InitParameterLoadRequest,
+    responses(
+        (status = 200, body = InitParameterLoadResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
 pub async fn init_parameter_load(
     State(state): State<Arc<AppState>>,
     Json(request): Json<InitParameterLoadRequest>,
@@ -86,10 +97,7 @@ pub async fn init_parameter_load(
 
     let session = Ed25519KeyPair::generate(&mut thread_rng());
     let session_vk = session.public();
-    let creation_time = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| EnclaveError::GenericError(format!("Time error: {}", e)))?
-        .as_millis() as u64;
+    let creation_time = current_timestamp_ms();
     let ttl_min = 10;
     let message = signed_message(
         SEAL_CONFIG.package_id.to_string(),
@@ -98,8 +106,9 @@ pub async fn init_parameter_load(
         ttl_min,
     );
 
+    let signing_key = state.signing_key().await;
     let sui_private_key = {
-        let priv_key_bytes = state.eph_kp.as_ref();
+        let priv_key_bytes = signing_key.keypair.as_ref();
         let key_bytes: [u8; 32] = priv_key_bytes
             .try_into()
             .expect("Invalid private key length");
@@ -147,6 +156,16 @@ pub async fn init_parameter_load(
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/seal/complete_parameter_load",
+    tag = "host",
+    request_body = CompleteParameterLoadRequest,
+    responses(
+        (status = 200, body = CompleteParameterLoadResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
 pub async fn complete_parameter_load(
     State(state): State<Arc<AppState>>,
     Json(request): Json<CompleteParameterLoadRequest>,
@@ -173,11 +192,7 @@ pub async fn complete_parameter_load(
         EnclaveError::GenericError(format!("Invalid UTF-8 in decrypted secret: {}", e))
     })?;
 
-    let parsed: FileKycConfig = serde_yaml::from_str(&raw)
-        .or_else(|_| serde_json::from_str(&raw))
-        .map_err(|e| EnclaveError::GenericError(format!("Invalid KYC config format: {}", e)))?;
-    let runtime =
-        KycRuntime::try_from(parsed).map_err(|e| EnclaveError::GenericError(e.to_string()))?;
+    let runtime = super::load_runtime(&state, &raw)?;
 
     {
         let mut guard = state.kyc_runtime.write().await;
@@ -189,9 +204,42 @@ pub async fn complete_parameter_load(
     }))
 }
 
+/// Seal-encrypts a KYC config under `id` for the key servers in `seal_config`,
+/// producing the object `complete_parameter_load` decrypts. Operators run this
+/// offline; `threshold` key servers must approve the enclave to decrypt it.
+pub fn encrypt_config(
+    seal_config: &SealConfig,
+    id: KeyId,
+    threshold: u8,
+    config: &[u8],
+) -> Result<EncryptedObject, EnclaveError> {
+    if threshold == 0 || threshold as usize > seal_config.key_servers.len() {
+        return Err(EnclaveError::GenericError(format!(
+            "threshold must be between 1 and {}",
+            seal_config.key_servers.len()
+        )));
+    }
+    let (encrypted, _) = seal_encrypt(
+        seal_config.package_id,
+        id,
+        seal_config.key_servers.clone(),
+        &IBEPublicKeys::BonehFranklinBLS12381(seal_config.public_keys.clone()),
+        threshold,
+        EncryptionInput::Aes256Gcm {
+            data: config.to_vec(),
+            aad: None,
+        },
+    )
+    .map_err(|e| EnclaveError::GenericError(format!("Seal encryption failed: {}", e)))?;
+    Ok(encrypted)
+}
+
 pub async fn spawn_host_init_server(state: Arc<AppState>) -> Result<(), EnclaveError> {
     let host_app = Router::new()
         .route("/ping", get(|| async { Json("pong") }))
+        .route("/rotate_key", post(rotate_key))
+        .route("/block_wallets", post(super::block_wallets))
+        .route("/unblock_wallets", post(super::unblock_wallets))
         .route("/seal/init_parameter_load", post(init_parameter_load))
         .route(
             "/seal/complete_parameter_load",
@@ -0,0 +1,133 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use isocountry::CountryCode;
+use std::collections::HashMap;
+
+/// Everyday names that differ from the ISO 3166 short names.
+const COMMON_NAMES: &[(&str, CountryCode)] = &[
+    ("united states", CountryCode::USA),
+    ("america", CountryCode::USA),
+    ("us", CountryCode::USA),
+    ("united kingdom", CountryCode::GBR),
+    ("great britain", CountryCode::GBR),
+    ("britain", CountryCode::GBR),
+    ("uk", CountryCode::GBR),
+    ("russia", CountryCode::RUS),
+    ("south korea", CountryCode::KOR),
+    ("north korea", CountryCode::PRK),
+    ("vietnam", CountryCode::VNM),
+    ("syria", CountryCode::SYR),
+    ("laos", CountryCode::LAO),
+    ("brunei", CountryCode::BRN),
+    ("czech republic", CountryCode::CZE),
+    ("turkiye", CountryCode::TUR),
+    ("holland", CountryCode::NLD),
+    ("ivory coast", CountryCode::CIV),
+    ("cape verde", CountryCode::CPV),
+    ("vatican", CountryCode::VAT),
+    ("vatican city", CountryCode::VAT),
+    ("north macedonia", CountryCode::MKD),
+    ("macedonia", CountryCode::MKD),
+    ("dr congo", CountryCode::COD),
+    ("democratic republic of the congo", CountryCode::COD),
+    ("republic of the congo", CountryCode::COG),
+    ("uae", CountryCode::ARE),
+    ("eswatini", CountryCode::SWZ),
+    ("burma", CountryCode::MMR),
+    ("east timor", CountryCode::TLS),
+];
+
+lazy_static::lazy_static! {
+    /// Normalized country name -> code, built from the ISO short names, their
+    /// forms without qualifiers ("Iran (Islamic Republic of)" -> "iran") and
+    /// `COMMON_NAMES`. Qualifier-less forms shared by two countries are dropped.
+    static ref COUNTRY_NAMES: HashMap<String, CountryCode> = {
+        let mut names = HashMap::new();
+        for code in CountryCode::iter() {
+            names.insert(normalize(code.name()), *code);
+        }
+        let mut short_forms: HashMap<String, Option<CountryCode>> = HashMap::new();
+        for code in CountryCode::iter() {
+            let short = code.name().split(['(', ',']).next().unwrap_or_default();
+            short_forms
+                .entry(normalize(short))
+                .and_modify(|existing| {
+                    if *existing != Some(*code) {
+                        *existing = None;
+                    }
+                })
+                .or_insert(Some(*code));
+        }
+        for (short, code) in short_forms {
+            if let Some(code) = code {
+                names.entry(short).or_insert(code);
+            }
+        }
+        for (name, code) in COMMON_NAMES {
+            names.insert(normalize(name), *code);
+        }
+        names
+    };
+}
+
+fn normalize(name: &str) -> String {
+    let ascii = deunicode::deunicode(name).to_lowercase();
+    ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Resolves an ISO 3166-1 alpha-2 or alpha-3 code, or a country name, to its
+/// alpha-2 code.
+pub fn normalize_country(input: &str) -> Option<&'static str> {
+    let trimmed = input.trim();
+    let code = match trimmed.len() {
+        2 => CountryCode::for_alpha2_caseless(trimmed).ok(),
+        3 => CountryCode::for_alpha3_caseless(trimmed).ok(),
+        _ => None,
+    };
+    code.or_else(|| COUNTRY_NAMES.get(&normalize(trimmed)).copied())
+        .map(|code| code.alpha2())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_resolve_to_alpha2() {
+        assert_eq!(normalize_country("DE"), Some("DE"));
+        assert_eq!(normalize_country("DEU"), Some("DE"));
+        assert_eq!(normalize_country(" deu "), Some("DE"));
+        assert_eq!(normalize_country("XX"), None);
+        assert_eq!(normalize_country("XXX"), None);
+    }
+
+    #[test]
+    fn names_resolve_to_alpha2() {
+        assert_eq!(normalize_country("Germany"), Some("DE"));
+        assert_eq!(normalize_country("gErMaNy"), Some("DE"));
+        assert_eq!(normalize_country("United States of America"), Some("US"));
+        assert_eq!(normalize_country("Côte d'Ivoire"), Some("CI"));
+    }
+
+    #[test]
+    fn qualifiers_and_common_names_resolve() {
+        assert_eq!(normalize_country("Iran"), Some("IR"));
+        assert_eq!(normalize_country("Bolivia"), Some("BO"));
+        assert_eq!(normalize_country("United States"), Some("US"));
+        assert_eq!(normalize_country("UK"), Some("GB"));
+        assert_eq!(normalize_country("Great Britain"), Some("GB"));
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        assert_eq!(normalize_country("Atlantis"), None);
+        assert_eq!(normalize_country(""), None);
+        // Shared by the Republic of Korea and the DPRK, so too ambiguous to keep.
+        assert_eq!(normalize_country("Korea"), None);
+    }
+}
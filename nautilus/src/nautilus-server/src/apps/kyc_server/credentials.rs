@@ -0,0 +1,386 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::predicates::date_from_ms;
+use super::{KycCredential, KycResponsePayload, KycRuntime};
+use crate::common::EnclaveKey;
+use crate::EnclaveError;
+use fastcrypto::ed25519::{Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::encoding::{Base58, Base64, Encoding, Hex};
+use fastcrypto::traits::{KeyPair, Signer, ToFromBytes, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::{IntoParams, ToSchema};
+
+/// Multicodec prefix of an Ed25519 public key (`ed25519-pub`, varint 0xed).
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+
+/// Output format of `/process_data`, chosen with the `format` query parameter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialFormat {
+    /// `ProcessedDataResponse` for the on-chain registry.
+    #[default]
+    Nautilus,
+    /// W3C Verifiable Credential secured as a JWT (`alg: EdDSA`).
+    JwtVc,
+    /// IETF SD-JWT VC with one disclosure per claim, bound to the holder key.
+    SdJwt,
+}
+
+impl CredentialFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialFormat::Nautilus => "nautilus",
+            CredentialFormat::JwtVc => "jwt_vc",
+            CredentialFormat::SdJwt => "sd_jwt",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProcessDataQuery {
+    #[serde(default)]
+    pub format: CredentialFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JwtVcCredential {
+    /// Compact JWS of the credential.
+    pub jwt_vc: String,
+    pub issuer: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum JwtVcError {
+    #[error("malformed JWT-VC: {0}")]
+    Malformed(String),
+    #[error("invalid issuer signature")]
+    InvalidSignature,
+    #[error("credential expired at {0}")]
+    Expired(u64),
+}
+
+/// `did:key` of an Ed25519 public key: multibase base58btc of the multicodec key.
+pub fn enclave_did(public_key: &[u8]) -> String {
+    format!("did:key:{}", multibase_key(public_key))
+}
+
+pub(crate) fn multibase_key(public_key: &[u8]) -> String {
+    let mut bytes = ED25519_MULTICODEC.to_vec();
+    bytes.extend_from_slice(public_key);
+    format!("z{}", Base58::encode(bytes))
+}
+
+pub(crate) fn base64url(bytes: &[u8]) -> String {
+    Base64::encode(bytes)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+pub(crate) fn base64url_decode(value: &str) -> Option<Vec<u8>> {
+    let mut standard = value.replace('-', "+").replace('_', "/");
+    standard.push_str(&"=".repeat((4 - standard.len() % 4) % 4));
+    Base64::decode(&standard).ok()
+}
+
+/// Signs `header.payload` with the enclave key and returns the compact JWS.
+pub(crate) fn sign_jws(
+    key: &EnclaveKey,
+    header: &serde_json::Value,
+    payload: &serde_json::Value,
+) -> String {
+    let signing_input = format!(
+        "{}.{}",
+        base64url(header.to_string().as_bytes()),
+        base64url(payload.to_string().as_bytes())
+    );
+    let signature = key.keypair.sign(signing_input.as_bytes());
+    format!("{}.{}", signing_input, base64url(signature.as_ref()))
+}
+
+/// Verifies a compact JWS signed with `EdDSA` and returns its header and payload.
+pub(crate) fn verify_jws<E>(
+    jws: &str,
+    public_key: &Ed25519PublicKey,
+    malformed: impl Fn(&str) -> E,
+    invalid_signature: impl FnOnce() -> E,
+) -> Result<(Value, Value), E> {
+    let mut parts = jws.split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(malformed("JWS must have three parts"));
+    };
+    let decode_json = |part: &str| {
+        base64url_decode(part)
+            .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+            .ok_or_else(|| malformed("JWS header and payload must be base64url JSON"))
+    };
+    let (header_json, payload_json) = (decode_json(header)?, decode_json(payload)?);
+    if header_json["alg"] != "EdDSA" {
+        return Err(malformed("JWS alg must be EdDSA"));
+    }
+    let signature = base64url_decode(signature)
+        .and_then(|bytes| Ed25519Signature::from_bytes(&bytes).ok())
+        .ok_or_else(|| malformed("JWS signature must be a base64url Ed25519 signature"))?;
+    public_key
+        .verify(format!("{}.{}", header, payload).as_bytes(), &signature)
+        .map_err(|_| invalid_signature())?;
+    Ok((header_json, payload_json))
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ` for a unix timestamp in milliseconds.
+pub(crate) fn rfc3339(ms: u64) -> String {
+    let (year, month, day) = date_from_ms(ms);
+    let seconds = (ms / 1000) % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// Evidence that ties a credential to the attested enclave: the key and PCRs a
+/// verifier should find in the document served by `/get_attestation`, and the
+/// config commitment placed in its `user_data`.
+pub(crate) fn attestation_evidence(key: &EnclaveKey, runtime: &KycRuntime) -> serde_json::Value {
+    json!({
+        "type": ["NitroEnclaveAttestation"],
+        "attestationEndpoint": "/get_attestation",
+        "enclavePublicKey": Hex::encode(key.keypair.public().as_bytes()),
+        "enclaveKeyId": key.key_id,
+        "enclaveMeasurement": runtime.enclave_measurement,
+        "configCommitment": Hex::encode(runtime.commitment().digest()),
+    })
+}
+
+/// Re-issues a signed KYC result as a JWT-VC from the same enclave key. The
+/// subject is the holder's `userWallet`, both as `sub` and `credentialSubject.id`.
+pub(crate) fn to_jwt_vc(
+    key: &EnclaveKey,
+    runtime: &KycRuntime,
+    credential: KycCredential,
+) -> Result<JwtVcCredential, EnclaveError> {
+    let KycCredential::Plain(signed) = credential else {
+        return Err(EnclaveError::GenericError(
+            "commitFields cannot be combined with format=jwt_vc".to_string(),
+        ));
+    };
+    let payload: KycResponsePayload = signed.response.data;
+    let mut subject = serde_json::to_value(&payload).expect("should not fail");
+    subject["id"] = json!(payload.userWallet);
+    let public_key = key.keypair.public().as_bytes().to_vec();
+    let issuer = enclave_did(&public_key);
+    let header = json!({
+        "alg": "EdDSA",
+        "typ": "JWT",
+        "kid": format!("{}#{}", issuer, multibase_key(&public_key)),
+    });
+    let claims = json!({
+        "iss": issuer,
+        "sub": payload.userWallet,
+        "jti": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "iat": payload.issuedAtMs / 1000,
+        "nbf": payload.issuedAtMs / 1000,
+        "exp": payload.expiresAtMs / 1000,
+        "vc": {
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "type": ["VerifiableCredential", "KycCredential"],
+            "issuer": issuer,
+            "issuanceDate": rfc3339(payload.issuedAtMs),
+            "expirationDate": rfc3339(payload.expiresAtMs),
+            "credentialSubject": subject,
+            "evidence": [attestation_evidence(key, runtime)],
+        },
+    });
+    Ok(JwtVcCredential {
+        jwt_vc: sign_jws(key, &header, &claims),
+        issuer,
+    })
+}
+
+/// Verifies a `format=jwt_vc` credential issued by the enclave key
+/// `issuer_public_key` and returns its `vc` claim, whose subject is `sub`.
+pub fn verify_jwt_vc(
+    jwt: &str,
+    issuer_public_key: &Ed25519PublicKey,
+    now_secs: u64,
+) -> Result<Value, JwtVcError> {
+    let malformed = |what: &str| JwtVcError::Malformed(what.to_string());
+    let (_, mut claims) = verify_jws(jwt, issuer_public_key, malformed, || {
+        JwtVcError::InvalidSignature
+    })?;
+    if claims["iss"] != enclave_did(issuer_public_key.as_bytes()).as_str() {
+        return Err(malformed("iss is not the did:key of the enclave key"));
+    }
+    let expires_at = claims["exp"]
+        .as_u64()
+        .ok_or_else(|| malformed("missing exp"))?;
+    if now_secs >= expires_at {
+        return Err(JwtVcError::Expired(expires_at));
+    }
+    let subject = &claims["vc"]["credentialSubject"];
+    if !claims["sub"].is_string() || subject["id"] != claims["sub"] {
+        return Err(malformed("credentialSubject.id must be the sub"));
+    }
+    Ok(claims["vc"].take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::FileKycConfig;
+    use crate::common::{to_signed_response, IntentScope};
+    use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PrivateKey};
+
+    const ISSUED_AT_MS: u64 = 1_750_000_000_000;
+    const EXPIRES_AT_MS: u64 = ISSUED_AT_MS + 86_400_000;
+    const NOW_SECS: u64 = ISSUED_AT_MS / 1000 + 60;
+    const WALLET: &str = "0x00000000000000000000000000000000000000000000000000000000000000ab";
+
+    fn runtime() -> KycRuntime {
+        let raw = format!(
+            "walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             providers:\n\
+             - provider_id: p1\n  \
+               public_key: \"0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad\"\n",
+            "00".repeat(144)
+        );
+        KycRuntime::try_from(FileKycConfig::parse(&raw).unwrap()).unwrap()
+    }
+
+    fn issue(key: &EnclaveKey) -> String {
+        let payload = serde_json::from_value(json!({
+            "userWallet": WALLET,
+            "providerId": "p1",
+            "kycLevel": 2,
+            "isPep": false,
+            "isSanctioned": false,
+            "blobId": "blob",
+            "docHash": "0xab",
+            "walrusCid": "blob",
+            "teeMeasurement": "0x00",
+            "nationality": "DE",
+            "cardArtCid": "cid",
+            "issuedAtMs": ISSUED_AT_MS,
+            "expiresAtMs": EXPIRES_AT_MS,
+            "sanctionsListVersion": null,
+            "pepMatchConfidence": 0,
+            "pepListVersion": null,
+            "predicates": [],
+        }))
+        .unwrap();
+        let signed = to_signed_response(&key.keypair, payload, 0, IntentScope::ProcessData);
+        to_jwt_vc(key, &runtime(), KycCredential::Plain(signed))
+            .unwrap()
+            .jwt_vc
+    }
+
+    /// Re-encodes the payload of `jwt` after `edit`, keeping its signature.
+    fn tamper(jwt: &str, edit: impl FnOnce(&mut Value)) -> String {
+        let mut parts: Vec<String> = jwt.split('.').map(str::to_string).collect();
+        let mut claims: Value =
+            serde_json::from_slice(&base64url_decode(&parts[1]).unwrap()).unwrap();
+        edit(&mut claims);
+        parts[1] = base64url(claims.to_string().as_bytes());
+        parts.join(".")
+    }
+
+    #[test]
+    fn did_key_matches_the_spec_vector() {
+        // did:key test vector for the Ed25519 key of the all-zero seed.
+        let keypair: Ed25519KeyPair = Ed25519PrivateKey::from_bytes(&[0; 32]).unwrap().into();
+        assert_eq!(
+            enclave_did(keypair.public().as_bytes()),
+            "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp"
+        );
+    }
+
+    #[test]
+    fn base64url_round_trips_without_padding() {
+        for bytes in [&b""[..], b"f", b"fo", b"foo", &[0xfb, 0xff, 0xfe]] {
+            let encoded = base64url(bytes);
+            assert!(!encoded.contains(['=', '+', '/']), "{}", encoded);
+            assert_eq!(base64url_decode(&encoded).unwrap(), bytes);
+        }
+        assert_eq!(base64url(&[0xfb, 0xff, 0xfe]), "-__-");
+        assert_eq!(base64url_decode("not base64!"), None);
+    }
+
+    #[test]
+    fn issued_jwt_vc_verifies() {
+        let key = EnclaveKey::generate(0);
+        let jwt = issue(&key);
+        let vc = verify_jwt_vc(&jwt, key.keypair.public(), NOW_SECS).unwrap();
+        assert_eq!(vc["issuer"], enclave_did(key.keypair.public().as_bytes()));
+        assert_eq!(vc["credentialSubject"]["id"], WALLET);
+        assert_eq!(vc["credentialSubject"]["kycLevel"], 2);
+        assert_eq!(vc["issuanceDate"], "2025-06-15T15:06:40Z");
+        assert_eq!(
+            vc["evidence"][0]["enclavePublicKey"],
+            Hex::encode(key.keypair.public().as_bytes())
+        );
+
+        assert!(matches!(
+            verify_jwt_vc(&jwt, key.keypair.public(), EXPIRES_AT_MS / 1000),
+            Err(JwtVcError::Expired(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_issuer_key_is_rejected() {
+        let jwt = issue(&EnclaveKey::generate(0));
+        assert!(matches!(
+            verify_jwt_vc(&jwt, EnclaveKey::generate(0).keypair.public(), NOW_SECS),
+            Err(JwtVcError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn tampered_payload_is_rejected() {
+        let key = EnclaveKey::generate(0);
+        let jwt = issue(&key);
+        let public_key = key.keypair.public();
+        for tampered in [
+            tamper(&jwt, |claims| {
+                claims["vc"]["credentialSubject"]["kycLevel"] = json!(3)
+            }),
+            tamper(&jwt, |claims| claims["exp"] = json!(u64::MAX)),
+            tamper(&jwt, |claims| claims["sub"] = json!("0x1")),
+        ] {
+            assert!(matches!(
+                verify_jwt_vc(&tampered, public_key, NOW_SECS),
+                Err(JwtVcError::InvalidSignature)
+            ));
+        }
+        assert!(matches!(
+            verify_jwt_vc("a.b", public_key, NOW_SECS),
+            Err(JwtVcError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn credentials_without_a_bound_subject_are_rejected() {
+        let key = EnclaveKey::generate(0);
+        let jwt = issue(&key);
+        let claims = tamper(&jwt, |claims| {
+            claims["vc"]["credentialSubject"]["id"] = json!("0x1");
+        });
+        let claims: Value =
+            serde_json::from_slice(&base64url_decode(claims.split('.').nth(1).unwrap()).unwrap())
+                .unwrap();
+        let resigned = sign_jws(&key, &json!({ "alg": "EdDSA", "typ": "JWT" }), &claims);
+        assert!(matches!(
+            verify_jwt_vc(&resigned, key.keypair.public(), NOW_SECS),
+            Err(JwtVcError::Malformed(_))
+        ));
+    }
+}
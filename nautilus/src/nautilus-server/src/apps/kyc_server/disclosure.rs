@@ -0,0 +1,282 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{JwtVcCredential, KycResponsePayload, PredicateResult, SdJwtCredential};
+use crate::common::{IntentMessage, ProcessedDataResponse};
+use fastcrypto::encoding::{Encoding, Hex};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+/// Fields of `KycResponsePayload` that can be replaced by salted commitments.
+pub const COMMITTABLE_FIELDS: [&str; 4] =
+    ["nationality", "isPep", "isSanctioned", "pepMatchConfidence"];
+
+const SALT_LEN: usize = 32;
+
+/// A committable field published in the clear.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DisclosedField {
+    pub name: String,
+    pub value: String,
+}
+
+/// SHA-256 commitment to a field; see `field_commitment`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldCommitment {
+    pub name: String,
+    pub commitment: Vec<u8>,
+}
+
+/// Opening of a `FieldCommitment`, handed only to the user who requested it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldOpening {
+    pub name: String,
+    pub value: String,
+    /// Hex encoded 32-byte salt.
+    pub salt: String,
+}
+
+/// `KycResponsePayload` with the chosen committable fields replaced by
+/// commitments, signed under `IntentScope::CommittedData`. The remaining
+/// committable fields are listed in `disclosed`.
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct KycCommittedResponsePayload {
+    pub userWallet: String,
+    pub providerId: String,
+    pub kycLevel: u8,
+    pub blobId: String,
+    pub docHash: String,
+    pub walrusCid: String,
+    pub teeMeasurement: String,
+    pub cardArtCid: String,
+    pub issuedAtMs: u64,
+    pub expiresAtMs: u64,
+    pub sanctionsListVersion: Option<String>,
+    pub pepListVersion: Option<String>,
+    pub predicates: Vec<PredicateResult>,
+    pub disclosed: Vec<DisclosedField>,
+    pub commitments: Vec<FieldCommitment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CommittedKycResponse {
+    pub signed: ProcessedDataResponse<IntentMessage<KycCommittedResponsePayload>>,
+    pub openings: Vec<FieldOpening>,
+}
+
+/// Result of a KYC issuance: the plain signed payload, the committed form when
+/// the request asked for `commitFields`, or the `jwt_vc` / `sd_jwt` formats.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum KycCredential {
+    Plain(ProcessedDataResponse<IntentMessage<KycResponsePayload>>),
+    Committed(CommittedKycResponse),
+    JwtVc(JwtVcCredential),
+    SdJwt(SdJwtCredential),
+}
+
+impl KycCredential {
+    /// Drops the openings of a committed credential, for copies that are stored
+    /// or sent anywhere but back to the requester.
+    pub fn without_openings(self) -> Self {
+        match self {
+            KycCredential::Committed(committed) => KycCredential::Committed(CommittedKycResponse {
+                openings: Vec::new(),
+                ..committed
+            }),
+            credential => credential,
+        }
+    }
+}
+
+/// SHA-256(salt || name || ":" || value). Booleans are `true`/`false`,
+/// `pepMatchConfidence` is its decimal code and `nationality` the alpha-2 code.
+pub fn field_commitment(name: &str, value: &str, salt: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(name.as_bytes());
+    hasher.update(b":");
+    hasher.update(value.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Checks a revealed field against the commitment published on-chain.
+pub fn verify_opening(commitment: &FieldCommitment, opening: &FieldOpening) -> bool {
+    let Ok(salt) = Hex::decode(&opening.salt) else {
+        return false;
+    };
+    commitment.name == opening.name
+        && field_commitment(&opening.name, &opening.value, &salt)[..] == commitment.commitment[..]
+}
+
+fn field_value(response: &KycResponsePayload, name: &str) -> String {
+    match name {
+        "nationality" => response.nationality.clone(),
+        "isPep" => response.isPep.to_string(),
+        "isSanctioned" => response.isSanctioned.to_string(),
+        "pepMatchConfidence" => (response.pepMatchConfidence as u8).to_string(),
+        _ => unreachable!("field names are checked against COMMITTABLE_FIELDS"),
+    }
+}
+
+/// Splits the committable fields of `response` into commitments (for names in
+/// `commit`, with fresh salts) and disclosed values.
+pub(crate) fn commit_fields(
+    response: KycResponsePayload,
+    commit: &[String],
+) -> (KycCommittedResponsePayload, Vec<FieldOpening>) {
+    let mut disclosed = Vec::new();
+    let mut commitments = Vec::new();
+    let mut openings = Vec::new();
+    for name in COMMITTABLE_FIELDS {
+        let value = field_value(&response, name);
+        if commit.iter().any(|field| field == name) {
+            let mut salt = [0u8; SALT_LEN];
+            rand::thread_rng().fill_bytes(&mut salt);
+            commitments.push(FieldCommitment {
+                name: name.to_string(),
+                commitment: field_commitment(name, &value, &salt).to_vec(),
+            });
+            openings.push(FieldOpening {
+                name: name.to_string(),
+                value,
+                salt: Hex::encode(salt),
+            });
+        } else {
+            disclosed.push(DisclosedField {
+                name: name.to_string(),
+                value,
+            });
+        }
+    }
+
+    let committed = KycCommittedResponsePayload {
+        userWallet: response.userWallet,
+        providerId: response.providerId,
+        kycLevel: response.kycLevel,
+        blobId: response.blobId,
+        docHash: response.docHash,
+        walrusCid: response.walrusCid,
+        teeMeasurement: response.teeMeasurement,
+        cardArtCid: response.cardArtCid,
+        issuedAtMs: response.issuedAtMs,
+        expiresAtMs: response.expiresAtMs,
+        sanctionsListVersion: response.sanctionsListVersion,
+        pepListVersion: response.pepListVersion,
+        predicates: response.predicates,
+        disclosed,
+        commitments,
+    };
+    (committed, openings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::PepMatchConfidence;
+
+    fn response() -> KycResponsePayload {
+        KycResponsePayload {
+            userWallet: "0x1".to_string(),
+            providerId: "p1".to_string(),
+            kycLevel: 2,
+            isPep: true,
+            isSanctioned: false,
+            blobId: "blob".to_string(),
+            docHash: "0xab".to_string(),
+            walrusCid: "blob".to_string(),
+            teeMeasurement: "0x00".to_string(),
+            nationality: "DE".to_string(),
+            cardArtCid: "cid".to_string(),
+            issuedAtMs: 0,
+            expiresAtMs: 1,
+            sanctionsListVersion: None,
+            pepMatchConfidence: PepMatchConfidence::Medium,
+            pepListVersion: None,
+            predicates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn field_commitment_hashes_salt_name_and_value() {
+        let salt = [7u8; SALT_LEN];
+        let expected: [u8; 32] = Sha256::new()
+            .chain_update(salt)
+            .chain_update(b"isPep:true")
+            .finalize()
+            .into();
+        assert_eq!(field_commitment("isPep", "true", &salt), expected);
+        assert_ne!(
+            field_commitment("isPep", "true", &salt),
+            field_commitment("isPep", "false", &salt)
+        );
+        assert_ne!(
+            field_commitment("isPep", "true", &salt),
+            field_commitment("isPep", "true", &[8u8; SALT_LEN])
+        );
+    }
+
+    #[test]
+    fn openings_verify_against_their_commitments() {
+        let commit = ["isPep".to_string(), "pepMatchConfidence".to_string()];
+        let (committed, openings) = commit_fields(response(), &commit);
+        assert_eq!(committed.commitments.len(), 2);
+        assert_eq!(openings.len(), 2);
+        let disclosed: Vec<&str> = committed
+            .disclosed
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(disclosed, ["nationality", "isSanctioned"]);
+        assert_eq!(openings[0].value, "true");
+        assert_eq!(openings[1].value, "2");
+        for (commitment, opening) in committed.commitments.iter().zip(&openings) {
+            assert!(verify_opening(commitment, opening));
+        }
+    }
+
+    #[test]
+    fn tampered_openings_are_rejected() {
+        let (committed, openings) = commit_fields(response(), &["isPep".to_string()]);
+        let commitment = &committed.commitments[0];
+        let opening = &openings[0];
+
+        let mut wrong_value = opening.clone();
+        wrong_value.value = "false".to_string();
+        assert!(!verify_opening(commitment, &wrong_value));
+
+        let mut wrong_salt = opening.clone();
+        wrong_salt.salt = Hex::encode([0u8; SALT_LEN]);
+        assert!(!verify_opening(commitment, &wrong_salt));
+
+        let mut bad_salt = opening.clone();
+        bad_salt.salt = "not hex".to_string();
+        assert!(!verify_opening(commitment, &bad_salt));
+
+        let mut wrong_name = opening.clone();
+        wrong_name.name = "isSanctioned".to_string();
+        assert!(!verify_opening(commitment, &wrong_name));
+    }
+
+    #[test]
+    fn without_openings_strips_committed_credentials() {
+        let (committed, openings) = commit_fields(response(), &["isPep".to_string()]);
+        let signed = ProcessedDataResponse {
+            response: IntentMessage {
+                intent: crate::common::IntentScope::CommittedData,
+                timestamp_ms: 0,
+                data: committed,
+            },
+            signature: String::new(),
+        };
+        let credential = KycCredential::Committed(CommittedKycResponse { signed, openings });
+        let KycCredential::Committed(stripped) = credential.without_openings() else {
+            panic!("expected a committed credential");
+        };
+        assert!(stripped.openings.is_empty());
+        assert_eq!(stripped.signed.response.data.commitments.len(), 1);
+    }
+}
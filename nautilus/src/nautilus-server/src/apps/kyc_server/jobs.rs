@@ -0,0 +1,458 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::validation;
+use super::{
+    authorize_payload, check_policy, ensure_runtime, issue_credential, KycCredential,
+    KycRequestPayload,
+};
+use crate::common::{current_timestamp_ms, EnclaveKey, ProcessDataRequest};
+use crate::AppState;
+use crate::{EnclaveError, ErrorResponse};
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::Signer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::info;
+use utoipa::ToSchema;
+
+/// Jobs kept in memory at once, finished or not.
+const MAX_JOBS: usize = 10_000;
+/// How long a finished job stays queryable.
+const JOB_RETENTION_MS: u64 = 60 * 60 * 1000;
+/// Webhook delivery attempts before giving up.
+const WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+/// Delay before the first retry; doubled after every failed attempt.
+const WEBHOOK_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Hosts the enclave can reach; the same file `health_check` probes.
+const ALLOWED_ENDPOINTS_PATH: &str = "allowed_endpoints.yaml";
+
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "x-kychook-timestamp";
+pub const WEBHOOK_KEY_ID_HEADER: &str = "x-kychook-key-id";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-kychook-signature";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// State of the webhook callback for a job.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub url: String,
+    pub attempts: u32,
+    pub delivered: bool,
+    pub last_error: Option<String>,
+}
+
+/// A job and its result. Results never carry commitment openings.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct KycJob {
+    pub job_id: String,
+    pub provider_id: String,
+    pub status: JobStatus,
+    pub created_at_ms: u64,
+    pub completed_at_ms: Option<u64>,
+    pub response: Option<KycCredential>,
+    pub error: Option<String>,
+    pub webhook: Option<WebhookDelivery>,
+}
+
+/// Body POSTed to the provider webhook once a job finishes.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct KycJobEvent {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub response: Option<KycCredential>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SubmitJobResponse {
+    pub job_id: String,
+}
+
+/// In-memory job table.
+#[derive(Default)]
+pub struct JobStore {
+    jobs: RwLock<HashMap<String, KycJob>>,
+}
+
+impl JobStore {
+    async fn insert(&self, job: KycJob) -> Result<(), EnclaveError> {
+        self.insert_at(job, current_timestamp_ms()).await
+    }
+
+    /// Drops jobs finished more than `JOB_RETENTION_MS` before `now`, then adds
+    /// `job` unless `MAX_JOBS` are still kept.
+    async fn insert_at(&self, job: KycJob, now: u64) -> Result<(), EnclaveError> {
+        let mut jobs = self.jobs.write().await;
+        jobs.retain(|_, job| {
+            job.completed_at_ms
+                .is_none_or(|done| now.saturating_sub(done) < JOB_RETENTION_MS)
+        });
+        if jobs.len() >= MAX_JOBS {
+            return Err(EnclaveError::GenericError(
+                "Too many jobs in flight, retry later".to_string(),
+            ));
+        }
+        jobs.insert(job.job_id.clone(), job);
+        Ok(())
+    }
+
+    pub async fn get(&self, job_id: &str) -> Option<KycJob> {
+        self.jobs.read().await.get(job_id).cloned()
+    }
+
+    async fn update(&self, job_id: &str, f: impl FnOnce(&mut KycJob)) {
+        if let Some(job) = self.jobs.write().await.get_mut(job_id) {
+            f(job);
+        }
+    }
+}
+
+/// Accepts a payload for background processing. The provider signature is
+/// checked before the job is queued; the Walrus download and signing happen
+/// afterwards, and the result goes to the provider's `webhook_url`, if any.
+#[utoipa::path(
+    post,
+    path = "/process_data_async",
+    tag = "kyc",
+    request_body = ProcessDataRequest<KycRequestPayload>,
+    responses(
+        (status = 202, description = "Job queued", body = SubmitJobResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "Refused by policy, with a signed denial", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse,
+            headers(("Retry-After" = u64, description = "Seconds to wait before retrying"))),
+    )
+)]
+pub async fn process_data_async(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<ProcessDataRequest<KycRequestPayload>>, JsonRejection>,
+) -> Result<(StatusCode, Json<SubmitJobResponse>), EnclaveError> {
+    let request = validation::parse_body(body)?;
+    validation::validate_payload(&request.payload)?;
+    validation::validate_async(&request.payload)?;
+    let runtime = ensure_runtime(&state).await?;
+    let provider = authorize_payload(&runtime, &request.payload)?.clone();
+    if let Some(url) = &provider.webhook_url {
+        check_webhook_egress(url)?;
+    }
+    let permit = state.rate_limiter.acquire(&runtime)?;
+    state
+        .rate_limiter
+        .check(&runtime, &provider, &request.payload)?;
+    check_policy(&state, &runtime, &provider, &request.payload).await?;
+
+    let job_id = uuid::Uuid::new_v4().to_string();
+    state
+        .kyc_jobs
+        .insert(KycJob {
+            job_id: job_id.clone(),
+            provider_id: provider.provider_id.clone(),
+            status: JobStatus::Pending,
+            created_at_ms: current_timestamp_ms(),
+            completed_at_ms: None,
+            response: None,
+            error: None,
+            webhook: provider.webhook_url.clone().map(|url| WebhookDelivery {
+                url,
+                attempts: 0,
+                delivered: false,
+                last_error: None,
+            }),
+        })
+        .await?;
+
+    let task_job_id = job_id.clone();
+    tokio::spawn(async move {
+        let outcome = issue_credential(&state, &runtime, &provider, &request.payload).await;
+        drop(permit);
+        let event = KycJobEvent {
+            job_id: task_job_id.clone(),
+            status: if outcome.is_ok() {
+                JobStatus::Succeeded
+            } else {
+                JobStatus::Failed
+            },
+            error: outcome.as_ref().err().map(|e| e.to_string()),
+            response: outcome.ok().map(KycCredential::without_openings),
+        };
+        state
+            .kyc_jobs
+            .update(&task_job_id, |job| {
+                job.status = event.status;
+                job.completed_at_ms = Some(current_timestamp_ms());
+                job.response = event.response.clone();
+                job.error = event.error.clone();
+            })
+            .await;
+        info!("job {} finished: {:?}", task_job_id, event.status);
+
+        if let Some(url) = provider.webhook_url {
+            deliver_webhook(&state, &url, &event).await;
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(SubmitJobResponse { job_id })))
+}
+
+/// Endpoint that reports the status (and, once done, the result) of a job.
+#[utoipa::path(
+    get,
+    path = "/jobs/{job_id}",
+    tag = "kyc",
+    params(("job_id" = String, Path)),
+    responses(
+        (status = 200, body = KycJob),
+        (status = 400, description = "Unknown job", body = ErrorResponse),
+    )
+)]
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<KycJob>, EnclaveError> {
+    state
+        .kyc_jobs
+        .get(&job_id)
+        .await
+        .map(Json)
+        .ok_or_else(|| EnclaveError::GenericError(format!("Unknown job {}", job_id)))
+}
+
+/// Reads the `endpoints` of `allowed_endpoints.yaml`.
+fn allowed_endpoints(path: &str) -> Result<Vec<String>, String> {
+    #[derive(Deserialize)]
+    struct AllowedEndpoints {
+        endpoints: Vec<String>,
+    }
+    let raw = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_yaml::from_str::<AllowedEndpoints>(&raw)
+        .map(|file| file.endpoints)
+        .map_err(|e| e.to_string())
+}
+
+/// Whether the host of `url` is one of the egress `endpoints`.
+fn is_egress_allowed(url: &str, endpoints: &[String]) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        .is_some_and(|host| endpoints.iter().any(|e| e.eq_ignore_ascii_case(&host)))
+}
+
+/// Refuses a job whose webhook the enclave could not reach: traffic only leaves
+/// the enclave for hosts in `allowed_endpoints.yaml`, and a delivery to any other
+/// host would fail only after the job was accepted.
+fn check_webhook_egress(url: &str) -> Result<(), EnclaveError> {
+    let endpoints = allowed_endpoints(ALLOWED_ENDPOINTS_PATH).map_err(|e| {
+        EnclaveError::GenericError(format!(
+            "Cannot check webhook_url against {}: {}",
+            ALLOWED_ENDPOINTS_PATH, e
+        ))
+    })?;
+    if is_egress_allowed(url, &endpoints) {
+        Ok(())
+    } else {
+        Err(EnclaveError::GenericError(format!(
+            "Provider webhook_url {} is not in {}, so the enclave cannot reach it",
+            url, ALLOWED_ENDPOINTS_PATH
+        )))
+    }
+}
+
+/// Message the enclave signs for a webhook delivery: `<timestamp>.<body>`.
+fn webhook_message(timestamp: &str, body: &str) -> Vec<u8> {
+    format!("{}.{}", timestamp, body).into_bytes()
+}
+
+/// Hex signature of a webhook delivery by `key`.
+fn sign_webhook(key: &EnclaveKey, timestamp: &str, body: &str) -> String {
+    Hex::encode(key.keypair.sign(&webhook_message(timestamp, body)))
+}
+
+/// Delay after failed delivery `attempt` (1-based), or None once the attempts
+/// are used up.
+fn webhook_backoff(attempt: u32) -> Option<Duration> {
+    (attempt < WEBHOOK_MAX_ATTEMPTS).then(|| WEBHOOK_INITIAL_BACKOFF * 2u32.pow(attempt - 1))
+}
+
+/// POSTs the event to the provider with exponential backoff. The body is signed
+/// by the current enclave key over `<timestamp>.<body>`, so providers can check
+/// it against `/keys` and reject replays.
+async fn deliver_webhook(state: &AppState, url: &str, event: &KycJobEvent) {
+    let body = serde_json::to_string(event).expect("should not fail");
+
+    for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+        let timestamp = current_timestamp_ms().to_string();
+        let signing_key = state.signing_key().await;
+        let signature = sign_webhook(&signing_key, &timestamp, &body);
+
+        let result = state
+            .walrus_client
+            .post(url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_TIMESTAMP_HEADER, &timestamp)
+            .header(WEBHOOK_KEY_ID_HEADER, &signing_key.key_id)
+            .header(WEBHOOK_SIGNATURE_HEADER, signature)
+            .body(body.clone())
+            .send()
+            .await;
+        let error = match result {
+            Ok(response) if response.status().is_success() => None,
+            Ok(response) => Some(format!("webhook returned status {}", response.status())),
+            Err(e) => Some(format!("webhook request failed: {}", e)),
+        };
+
+        let delivered = error.is_none();
+        state
+            .kyc_jobs
+            .update(&event.job_id, |job| {
+                if let Some(webhook) = job.webhook.as_mut() {
+                    webhook.attempts = attempt;
+                    webhook.delivered = delivered;
+                    webhook.last_error = error.clone();
+                }
+            })
+            .await;
+        if delivered {
+            return;
+        }
+        info!(
+            "webhook for job {} failed (attempt {}): {:?}",
+            event.job_id, attempt, error
+        );
+        if let Some(backoff) = webhook_backoff(attempt) {
+            tokio::time::sleep(backoff).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::ed25519::Ed25519Signature;
+    use fastcrypto::traits::{KeyPair, ToFromBytes, VerifyingKey};
+
+    fn job(job_id: &str, completed_at_ms: Option<u64>) -> KycJob {
+        KycJob {
+            job_id: job_id.to_string(),
+            provider_id: "p1".to_string(),
+            status: JobStatus::Pending,
+            created_at_ms: 0,
+            completed_at_ms,
+            response: None,
+            error: None,
+            webhook: None,
+        }
+    }
+
+    #[test]
+    fn webhook_signature_covers_timestamp_and_body() {
+        let key = EnclaveKey::generate(0);
+        let body = r#"{"job_id":"j1"}"#;
+        assert_eq!(webhook_message("1700", body), br#"1700.{"job_id":"j1"}"#);
+
+        let signature = sign_webhook(&key, "1700", body);
+        let signature = Ed25519Signature::from_bytes(&Hex::decode(&signature).unwrap()).unwrap();
+        let public_key = key.keypair.public();
+        assert!(public_key
+            .verify(&webhook_message("1700", body), &signature)
+            .is_ok());
+        assert!(public_key
+            .verify(&webhook_message("1701", body), &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn webhook_retries_back_off_exponentially() {
+        let delays: Vec<Option<Duration>> =
+            (1..=WEBHOOK_MAX_ATTEMPTS).map(webhook_backoff).collect();
+        let secs = |s| Some(Duration::from_secs(s));
+        assert_eq!(delays, [secs(1), secs(2), secs(4), secs(8), None]);
+    }
+
+    #[test]
+    fn webhook_hosts_must_be_allowed_endpoints() {
+        let endpoints = vec!["hooks.example".to_string(), "Other.Example".to_string()];
+        assert!(is_egress_allowed("https://hooks.example/kyc", &endpoints));
+        assert!(is_egress_allowed(
+            "https://HOOKS.example:8443/kyc",
+            &endpoints
+        ));
+        assert!(is_egress_allowed("https://other.example", &endpoints));
+        assert!(!is_egress_allowed(
+            "https://evil.hooks.example/kyc",
+            &endpoints
+        ));
+        assert!(!is_egress_allowed(
+            "https://hooks.example.evil/kyc",
+            &endpoints
+        ));
+        assert!(!is_egress_allowed("not a url", &endpoints));
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/apps/kyc_server/allowed_endpoints.yaml"
+        );
+        let endpoints = allowed_endpoints(path).unwrap();
+        assert!(is_egress_allowed(
+            "https://aggregator.walrus-testnet.walrus.space/hook",
+            &endpoints
+        ));
+        assert!(allowed_endpoints("missing.yaml").is_err());
+    }
+
+    #[tokio::test]
+    async fn finished_jobs_are_evicted_after_retention() {
+        let store = JobStore::default();
+        store.insert_at(job("pending", None), 0).await.unwrap();
+        store.insert_at(job("finished", Some(0)), 0).await.unwrap();
+
+        store
+            .insert_at(job("late", None), JOB_RETENTION_MS - 1)
+            .await
+            .unwrap();
+        assert!(store.get("finished").await.is_some());
+        store
+            .insert_at(job("later", None), JOB_RETENTION_MS)
+            .await
+            .unwrap();
+        assert!(store.get("finished").await.is_none());
+        // Jobs still running are never dropped.
+        assert!(store.get("pending").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn full_store_refuses_new_jobs() {
+        let store = JobStore::default();
+        for index in 0..MAX_JOBS - 1 {
+            store
+                .insert_at(job(&index.to_string(), None), 0)
+                .await
+                .unwrap();
+        }
+        store.insert_at(job("done", Some(0)), 0).await.unwrap();
+        assert!(store.insert_at(job("over", None), 0).await.is_err());
+
+        // Room is made as soon as a finished job expires.
+        store
+            .insert_at(job("next", None), JOB_RETENTION_MS)
+            .await
+            .unwrap();
+        assert!(store.get("done").await.is_none());
+        assert!(store.get("next").await.is_some());
+    }
+}
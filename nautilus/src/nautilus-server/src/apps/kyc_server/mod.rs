@@ -1,14 +1,44 @@
+mod blocklist;
 mod bootstrap;
+mod countries;
+mod credentials;
+mod disclosure;
+mod jobs;
+mod openapi;
+mod predicates;
+mod proof;
+mod rate_limit;
+mod revocation;
+mod screening;
+mod sd_jwt;
 mod types;
+mod validation;
 
-pub use bootstrap::{complete_parameter_load, init_parameter_load, spawn_host_init_server};
+pub use blocklist::*;
+pub use bootstrap::{
+    complete_parameter_load, encrypt_config, init_parameter_load, spawn_host_init_server,
+};
+pub use countries::*;
+pub use credentials::{
+    enclave_did, verify_jwt_vc, CredentialFormat, JwtVcCredential, JwtVcError, ProcessDataQuery,
+};
+pub use disclosure::*;
+pub use jobs::*;
+pub use openapi::{openapi_json, ApiDoc};
+pub use predicates::*;
+pub use proof::KycProof;
+pub use rate_limit::*;
+pub use revocation::*;
+pub use screening::*;
+pub use sd_jwt::*;
 pub use types::*;
+pub use validation::{MAX_KYC_LEVEL, MIN_KYC_LEVEL};
 
-use crate::common::IntentMessage;
-use crate::common::{to_signed_response, IntentScope, ProcessDataRequest, ProcessedDataResponse};
+use crate::common::{current_timestamp_ms, to_signed_response, IntentScope, ProcessDataRequest};
 use crate::AppState;
-use crate::EnclaveError;
-use axum::extract::State;
+use crate::{EnclaveError, ErrorResponse, FieldViolation};
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{Query, State};
 use axum::Json;
 use fastcrypto::ed25519::Ed25519Signature;
 use fastcrypto::encoding::{Encoding, Hex};
@@ -17,26 +47,55 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use utoipa::ToSchema;
+
+/// Largest number of payloads accepted by `process_data_batch`.
+const MAX_BATCH_SIZE: usize = 64;
+/// Payloads of a batch verified concurrently (signature check + Walrus fetch).
+const BATCH_PARALLELISM: usize = 8;
 
 #[allow(non_snake_case)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct KycRequestPayload {
     pub userWallet: String,
     pub providerId: String,
     pub kycLevel: u8,
-    pub isPep: bool,
-    pub isSanctioned: bool,
     pub blobId: String,
     pub docHash: String,
     pub walrusBlobObject: Option<String>,
     pub nationality: String,
     pub cardArtCid: String,
     pub providerSignature: Option<String>,
+    /// Expiry of the identity document, as declared by the provider.
+    #[serde(default)]
+    pub documentExpiresAtMs: Option<u64>,
+    /// Applicant's name as on the identity document; required for screening.
+    #[serde(default)]
+    pub fullName: Option<String>,
+    /// Applicant's date of birth, `YYYY-MM-DD`.
+    #[serde(default)]
+    pub dateOfBirth: Option<String>,
+    /// `COMMITTABLE_FIELDS` to sign as salted commitments instead of in the clear.
+    #[serde(default)]
+    pub commitFields: Option<Vec<String>>,
+    /// Country of residence, used only to evaluate predicates.
+    #[serde(default)]
+    pub residenceCountry: Option<String>,
+    /// Names of configured predicates to evaluate and sign.
+    #[serde(default)]
+    pub requestedPredicates: Option<Vec<String>>,
+    /// Hex Ed25519 public key of `userWallet`, bound into `format=sd_jwt`
+    /// credentials.
+    #[serde(default)]
+    pub holderPublicKey: Option<String>,
 }
 
 #[allow(non_snake_case)]
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct KycResponsePayload {
     pub userWallet: String,
     pub providerId: String,
@@ -49,6 +108,19 @@ pub struct KycResponsePayload {
     pub teeMeasurement: String,
     pub nationality: String,
     pub cardArtCid: String,
+    pub issuedAtMs: u64,
+    pub expiresAtMs: u64,
+    /// Version of the sanctions list screened against. `None` means the applicant
+    /// was not screened, and `isSanctioned` is then false without meaning.
+    pub sanctionsListVersion: Option<String>,
+    /// Closeness of the best PEP list match; `None` (0) when nothing matched.
+    /// `isPep` is true exactly when this is not `None`.
+    pub pepMatchConfidence: PepMatchConfidence,
+    /// Version of the PEP list screened against; `None` means the applicant was
+    /// not screened.
+    pub pepListVersion: Option<String>,
+    /// Results of the requested predicates; their inputs are not signed.
+    pub predicates: Vec<PredicateResult>,
 }
 
 fn normalize_hex(value: &str) -> String {
@@ -59,15 +131,122 @@ fn normalize_hex(value: &str) -> String {
     }
 }
 
-fn canonical_message(payload: &KycRequestPayload) -> Vec<u8> {
-    format!(
-        "{}::{}::{}::{}::{}",
-        payload.providerId, payload.userWallet, payload.blobId, payload.docHash, payload.kycLevel
-    )
-    .into_bytes()
+/// Prefix of the message behind `providerSignature`, naming its format. Version
+/// 1 joined the fields with `::`, which let free text in one field pass for
+/// another; it is no longer accepted.
+pub const PROVIDER_MESSAGE_PREFIX: &[u8] = b"kychook-provider-message:v2:";
+
+/// Fields covered by `providerSignature`, BCS encoded so every string is length
+/// prefixed and every optional field is tagged.
+#[allow(non_snake_case)]
+#[derive(Serialize)]
+struct ProviderMessage<'a> {
+    providerId: &'a str,
+    userWallet: &'a str,
+    blobId: &'a str,
+    docHash: &'a str,
+    kycLevel: u8,
+    nationality: &'a str,
+    documentExpiresAtMs: Option<u64>,
+    fullName: Option<&'a str>,
+    dateOfBirth: Option<&'a str>,
+    residenceCountry: Option<&'a str>,
+}
+
+/// Message a provider signs with its Ed25519 key to produce `providerSignature`:
+/// `PROVIDER_MESSAGE_PREFIX` followed by the BCS encoding of the signed fields.
+/// `nationality` is signed as sent, before normalization, since policy checks
+/// and screening depend on it.
+pub fn canonical_message(payload: &KycRequestPayload) -> Vec<u8> {
+    let message = ProviderMessage {
+        providerId: &payload.providerId,
+        userWallet: &payload.userWallet,
+        blobId: &payload.blobId,
+        docHash: &payload.docHash,
+        kycLevel: payload.kycLevel,
+        nationality: &payload.nationality,
+        documentExpiresAtMs: payload.documentExpiresAtMs,
+        fullName: payload.fullName.as_deref(),
+        dateOfBirth: payload.dateOfBirth.as_deref(),
+        residenceCountry: payload.residenceCountry.as_deref(),
+    };
+    let mut bytes = PROVIDER_MESSAGE_PREFIX.to_vec();
+    bytes.extend(bcs::to_bytes(&message).expect("should not fail"));
+    bytes
 }
 
-fn verify_provider_signature(
+/// Computes the credential expiry, capped by the declared document expiry.
+fn credential_expiry(
+    runtime: &KycRuntime,
+    provider: &ProviderRuntime,
+    payload: &KycRequestPayload,
+    issued_at_ms: u64,
+) -> Result<u64, EnclaveError> {
+    let expires_at_ms =
+        issued_at_ms.saturating_add(runtime.validity_ms(provider, payload.kycLevel));
+    match payload.documentExpiresAtMs {
+        Some(document_expiry) if document_expiry <= issued_at_ms => Err(
+            EnclaveError::GenericError(format!("Identity document expired at {}", document_expiry)),
+        ),
+        Some(document_expiry) => Ok(expires_at_ms.min(document_expiry)),
+        None => Ok(expires_at_ms),
+    }
+}
+
+/// Result of screening an applicant against the configured lists.
+struct ScreeningOutcome {
+    is_sanctioned: bool,
+    sanctions_list_version: Option<String>,
+    is_pep: bool,
+    pep_match_confidence: PepMatchConfidence,
+    pep_list_version: Option<String>,
+}
+
+/// Screens the applicant against the configured sanctions and PEP lists.
+/// `isSanctioned` and `isPep` are only ever the enclave's own findings, never the
+/// provider's.
+fn screen_applicant(
+    runtime: &KycRuntime,
+    provider: &ProviderRuntime,
+    payload: &KycRequestPayload,
+    nationality: &str,
+) -> Result<ScreeningOutcome, EnclaveError> {
+    let mut outcome = ScreeningOutcome {
+        is_sanctioned: false,
+        sanctions_list_version: None,
+        is_pep: false,
+        pep_match_confidence: PepMatchConfidence::None,
+        pep_list_version: None,
+    };
+    if runtime.sanctions.is_none() && runtime.pep.is_none() {
+        return Ok(outcome);
+    }
+    let full_name = payload.fullName.as_deref().ok_or_else(|| {
+        EnclaveError::GenericError("fullName is required for applicant screening".to_string())
+    })?;
+    // Attributes rule list entries out, so only use them when the provider
+    // signature covers them; anyone holding the payload could edit them otherwise.
+    let signed = !provider.allow_unsigned;
+    let applicant = Applicant {
+        full_name,
+        date_of_birth: payload.dateOfBirth.as_deref().filter(|_| signed),
+        nationality: Some(nationality).filter(|_| signed),
+    };
+    if let Some(list) = &runtime.sanctions {
+        outcome.is_sanctioned = list.is_listed(&applicant);
+        outcome.sanctions_list_version = Some(list.version.clone());
+    }
+    if let Some(list) = &runtime.pep {
+        outcome.pep_match_confidence = list.screen(&applicant);
+        outcome.is_pep = outcome.pep_match_confidence != PepMatchConfidence::None;
+        outcome.pep_list_version = Some(list.version.clone());
+    }
+    Ok(outcome)
+}
+
+/// Checks `providerSignature` over `canonical_message(payload)` against the key
+/// of `provider`; unsigned payloads pass only if the provider allows them.
+pub fn verify_provider_signature(
     provider: &ProviderRuntime,
     payload: &KycRequestPayload,
 ) -> Result<(), EnclaveError> {
@@ -78,6 +257,15 @@ fn verify_provider_signature(
         .providerSignature
         .as_ref()
         .ok_or_else(|| EnclaveError::GenericError("Missing providerSignature".to_string()))?;
+    verify_provider_message(provider, signature_hex, &canonical_message(payload))
+}
+
+/// Checks a hex encoded Ed25519 signature from `provider` over `message`.
+pub(crate) fn verify_provider_message(
+    provider: &ProviderRuntime,
+    signature_hex: &str,
+    message: &[u8],
+) -> Result<(), EnclaveError> {
     let sig_bytes = Hex::decode(signature_hex.trim_start_matches("0x")).map_err(|e| {
         EnclaveError::GenericError(format!("Invalid provider signature encoding: {}", e))
     })?;
@@ -85,7 +273,7 @@ fn verify_provider_signature(
         .map_err(|e| EnclaveError::GenericError(format!("Invalid signature: {}", e)))?;
     provider
         .public_key
-        .verify(&canonical_message(payload), &sig)
+        .verify(message, &sig)
         .map_err(|_| EnclaveError::GenericError("Provider signature mismatch".to_string()))
 }
 
@@ -113,9 +301,13 @@ async fn fetch_walrus_blob(
         .map_err(|e| EnclaveError::GenericError(format!("Failed to read Walrus blob: {}", e)))
 }
 
+/// `docHash` of a document: `0x` followed by its hex SHA-256.
+pub fn doc_hash(document: &[u8]) -> String {
+    format!("0x{}", Hex::encode(Sha256::digest(document)))
+}
+
 fn validate_doc_hash(expected: &str, actual_blob: &[u8]) -> Result<(), EnclaveError> {
-    let digest = Sha256::digest(actual_blob);
-    let computed = format!("0x{}", Hex::encode(digest));
+    let computed = doc_hash(actual_blob);
     let normalized_expected = normalize_hex(expected).to_lowercase();
     if computed.to_lowercase() == normalized_expected {
         Ok(())
@@ -134,14 +326,21 @@ fn walrus_cid(payload: &KycRequestPayload) -> String {
         .unwrap_or_else(|| payload.blobId.clone())
 }
 
-fn intent_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_else(|_| Duration::from_secs(0))
-        .as_millis() as u64
+/// Parses a YAML or JSON `FileKycConfig` and binds it to the enclave's measured PCRs.
+pub(crate) fn load_runtime(state: &AppState, raw: &str) -> Result<KycRuntime, EnclaveError> {
+    let parsed =
+        FileKycConfig::parse(raw).map_err(|e| EnclaveError::GenericError(e.to_string()))?;
+    let mut runtime =
+        KycRuntime::try_from(parsed).map_err(|e| EnclaveError::GenericError(e.to_string()))?;
+    if let Some(measurement) = &state.measurement {
+        runtime
+            .bind_measurement(measurement)
+            .map_err(|e| EnclaveError::GenericError(e.to_string()))?;
+    }
+    Ok(runtime)
 }
 
-async fn ensure_runtime(state: &AppState) -> Result<KycRuntime, EnclaveError> {
+pub(crate) async fn ensure_runtime(state: &AppState) -> Result<KycRuntime, EnclaveError> {
     if let Some(runtime) = state.kyc_runtime.read().await.clone() {
         return Ok(runtime);
     }
@@ -154,11 +353,7 @@ async fn ensure_runtime(state: &AppState) -> Result<KycRuntime, EnclaveError> {
     let raw = std::fs::read_to_string(&path).map_err(|e| {
         EnclaveError::GenericError(format!("Failed to read KYC config file: {}", e))
     })?;
-    let parsed: FileKycConfig = serde_yaml::from_str(&raw)
-        .or_else(|_| serde_json::from_str(&raw))
-        .map_err(|e| EnclaveError::GenericError(format!("Invalid KYC config format: {}", e)))?;
-    let runtime =
-        KycRuntime::try_from(parsed).map_err(|e| EnclaveError::GenericError(e.to_string()))?;
+    let runtime = load_runtime(state, &raw)?;
     {
         let mut guard = state.kyc_runtime.write().await;
         *guard = Some(runtime.clone());
@@ -166,42 +361,530 @@ async fn ensure_runtime(state: &AppState) -> Result<KycRuntime, EnclaveError> {
     Ok(runtime)
 }
 
-pub async fn process_data(
-    State(state): State<Arc<AppState>>,
-    Json(request): Json<ProcessDataRequest<KycRequestPayload>>,
-) -> Result<Json<ProcessedDataResponse<IntentMessage<KycResponsePayload>>>, EnclaveError> {
-    let runtime = ensure_runtime(&state).await?;
-    let provider = runtime
-        .find_provider(&request.payload.providerId)
-        .ok_or_else(|| {
-            EnclaveError::GenericError(format!("Unknown provider {}", request.payload.providerId))
-        })?;
+/// Resolves the payload's provider and checks its signature.
+fn authorize_payload<'a>(
+    runtime: &'a KycRuntime,
+    payload: &KycRequestPayload,
+) -> Result<&'a ProviderRuntime, EnclaveError> {
+    let provider = runtime.find_provider(&payload.providerId).ok_or_else(|| {
+        EnclaveError::GenericError(format!("Unknown provider {}", payload.providerId))
+    })?;
+    verify_provider_signature(provider, payload)?;
+    Ok(provider)
+}
 
-    verify_provider_signature(provider, &request.payload)?;
+/// Fetches the Walrus blob, checks its hash and signs the KYC result for an
+/// already authorized payload.
+async fn issue_credential(
+    state: &AppState,
+    runtime: &KycRuntime,
+    provider: &ProviderRuntime,
+    payload: &KycRequestPayload,
+) -> Result<KycCredential, EnclaveError> {
+    let nationality = check_policy(state, runtime, provider, payload).await?;
+    let screening = screen_applicant(runtime, provider, payload, nationality)?;
+    let blob_bytes = fetch_walrus_blob(&state.walrus_client, runtime, &payload.blobId).await?;
+    validate_doc_hash(&payload.docHash, &blob_bytes)?;
 
-    let blob_bytes =
-        fetch_walrus_blob(&state.walrus_client, &runtime, &request.payload.blobId).await?;
-    validate_doc_hash(&request.payload.docHash, &blob_bytes)?;
+    let issued_at_ms = current_timestamp_ms();
+    let expires_at_ms = credential_expiry(runtime, provider, payload, issued_at_ms)?;
+    let predicates = evaluate_predicates(&runtime.predicates, payload, nationality, issued_at_ms)?;
 
-    let walrus_cid = walrus_cid(&request.payload);
     let response = KycResponsePayload {
-        userWallet: request.payload.userWallet.clone(),
+        userWallet: normalize_sui_address(&payload.userWallet)
+            .ok_or_else(|| EnclaveError::GenericError("Invalid userWallet".to_string()))?,
         providerId: provider.provider_id.clone(),
-        kycLevel: request.payload.kycLevel,
-        isPep: request.payload.isPep,
-        isSanctioned: request.payload.isSanctioned,
-        blobId: request.payload.blobId.clone(),
-        docHash: normalize_hex(&request.payload.docHash),
-        walrusCid: walrus_cid,
+        kycLevel: payload.kycLevel,
+        isPep: screening.is_pep,
+        isSanctioned: screening.is_sanctioned,
+        blobId: payload.blobId.clone(),
+        docHash: normalize_hex(&payload.docHash),
+        walrusCid: walrus_cid(payload),
         teeMeasurement: runtime.enclave_measurement.clone(),
-        nationality: request.payload.nationality.clone(),
-        cardArtCid: request.payload.cardArtCid.clone(),
+        nationality: nationality.to_string(),
+        cardArtCid: payload.cardArtCid.clone(),
+        issuedAtMs: issued_at_ms,
+        expiresAtMs: expires_at_ms,
+        sanctionsListVersion: screening.sanctions_list_version,
+        pepMatchConfidence: screening.pep_match_confidence,
+        pepListVersion: screening.pep_list_version,
+        predicates,
     };
 
-    Ok(Json(to_signed_response(
-        &state.eph_kp,
-        response,
-        intent_timestamp(),
-        IntentScope::ProcessData,
-    )))
+    let signing_key = state.signing_key().await;
+    match payload.commitFields.as_deref() {
+        Some(fields) if !fields.is_empty() => {
+            let (committed, openings) = commit_fields(response, fields);
+            Ok(KycCredential::Committed(CommittedKycResponse {
+                signed: to_signed_response(
+                    &signing_key.keypair,
+                    committed,
+                    issued_at_ms,
+                    IntentScope::CommittedData,
+                ),
+                openings,
+            }))
+        }
+        _ => Ok(KycCredential::Plain(to_signed_response(
+            &signing_key.keypair,
+            response,
+            issued_at_ms,
+            IntentScope::ProcessData,
+        ))),
+    }
+}
+
+/// Validates and verifies a single payload and returns the enclave-signed KYC result.
+async fn process_payload(
+    state: &AppState,
+    runtime: &KycRuntime,
+    payload: &KycRequestPayload,
+) -> Result<KycCredential, EnclaveError> {
+    validation::validate_payload(payload)?;
+    let provider = authorize_payload(runtime, payload)?;
+    state.rate_limiter.check(runtime, provider, payload)?;
+    issue_credential(state, runtime, provider, payload).await
+}
+
+/// Endpoint that verifies a provider payload and returns the enclave-signed KYC
+/// result in the requested `format`.
+#[utoipa::path(
+    post,
+    path = "/process_data",
+    tag = "kyc",
+    params(ProcessDataQuery),
+    request_body = ProcessDataRequest<KycRequestPayload>,
+    responses(
+        (status = 200, body = KycCredential),
+        (status = 400, description = "Invalid request or failed verification", body = ErrorResponse),
+        (status = 403, description = "Refused by policy, with a signed denial", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse,
+            headers(("Retry-After" = u64, description = "Seconds to wait before retrying"))),
+    )
+)]
+pub async fn process_data(
+    State(state): State<Arc<AppState>>,
+    query: Result<Query<ProcessDataQuery>, QueryRejection>,
+    body: Result<Json<ProcessDataRequest<KycRequestPayload>>, JsonRejection>,
+) -> Result<Json<KycCredential>, EnclaveError> {
+    let Query(query) = query.map_err(|rejection| {
+        EnclaveError::InvalidRequest(vec![FieldViolation::new("format", rejection.body_text())])
+    })?;
+    let request = validation::parse_body(body)?;
+    validation::validate_format(query.format, &request.payload)?;
+    let runtime = ensure_runtime(&state).await?;
+    let _permit = state.rate_limiter.acquire(&runtime)?;
+    let credential = process_payload(&state, &runtime, &request.payload).await?;
+    Ok(Json(match query.format {
+        CredentialFormat::Nautilus => credential,
+        CredentialFormat::JwtVc => {
+            let signing_key = state.signing_key().await;
+            KycCredential::JwtVc(credentials::to_jwt_vc(&signing_key, &runtime, credential)?)
+        }
+        CredentialFormat::SdJwt => {
+            let holder_public_key = request
+                .payload
+                .holderPublicKey
+                .as_deref()
+                .and_then(validation::decode_hex)
+                .expect("checked by validate_payload");
+            let signing_key = state.signing_key().await;
+            KycCredential::SdJwt(sd_jwt::to_sd_jwt(
+                &signing_key,
+                credential,
+                &holder_public_key,
+            )?)
+        }
+    }))
+}
+
+/// Outcome for one entry of a batch, in request order.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProcessDataBatchItem {
+    pub index: usize,
+    pub response: Option<KycCredential>,
+    /// The body `/process_data` would have returned for this entry.
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProcessDataBatchResponse {
+    pub results: Vec<ProcessDataBatchItem>,
+}
+
+/// Processes up to `MAX_BATCH_SIZE` payloads, at most `BATCH_PARALLELISM` at a
+/// time. A failing entry, including one over a rate limit, is reported in its own
+/// slot and does not fail the batch.
+#[utoipa::path(
+    post,
+    path = "/process_data_batch",
+    tag = "kyc",
+    request_body = ProcessDataRequest<Vec<KycRequestPayload>>,
+    responses(
+        (status = 200, body = ProcessDataBatchResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn process_data_batch(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<ProcessDataRequest<Vec<KycRequestPayload>>>, JsonRejection>,
+) -> Result<Json<ProcessDataBatchResponse>, EnclaveError> {
+    let payloads = validation::parse_body(body)?.payload;
+    if payloads.is_empty() || payloads.len() > MAX_BATCH_SIZE {
+        return Err(EnclaveError::InvalidRequest(vec![FieldViolation::new(
+            "payload",
+            format!("must contain between 1 and {} payloads", MAX_BATCH_SIZE),
+        )]));
+    }
+
+    let runtime = Arc::new(ensure_runtime(&state).await?);
+    let permits = Arc::new(Semaphore::new(BATCH_PARALLELISM));
+    let mut results: Vec<ProcessDataBatchItem> = (0..payloads.len())
+        .map(|index| ProcessDataBatchItem {
+            index,
+            response: None,
+            error: Some(EnclaveError::GenericError("Item was not processed".to_string()).into()),
+        })
+        .collect();
+
+    let mut tasks = JoinSet::new();
+    for (index, payload) in payloads.into_iter().enumerate() {
+        let state = state.clone();
+        let runtime = runtime.clone();
+        let permits = permits.clone();
+        tasks.spawn(async move {
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .expect("batch semaphore is never closed");
+            // Every entry holds its own slot of `max_concurrent_requests`.
+            let outcome = match state.rate_limiter.acquire(&runtime) {
+                Ok(_slot) => process_payload(&state, &runtime, &payload).await,
+                Err(e) => Err(e),
+            };
+            (index, outcome)
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((index, outcome)) = joined else {
+            continue;
+        };
+        results[index] = match outcome {
+            Ok(response) => ProcessDataBatchItem {
+                index,
+                response: Some(response),
+                error: None,
+            },
+            Err(e) => ProcessDataBatchItem {
+                index,
+                response: None,
+                error: Some(e.into()),
+            },
+        };
+    }
+
+    Ok(Json(ProcessDataBatchResponse { results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{EnclaveKey, KeyRing, NitroAttestor};
+    use axum::routing::get;
+    use axum::Router;
+    use serde_json::json;
+    use tokio::sync::RwLock;
+
+    const DOCUMENT: &[u8] = b"passport scan";
+
+    fn payload(extra: serde_json::Value) -> KycRequestPayload {
+        let mut value = json!({
+            "userWallet": "0x1",
+            "providerId": "p1",
+            "kycLevel": 2,
+            "blobId": "blob",
+            "docHash": "0xab",
+            "walrusBlobObject": null,
+            "nationality": "DE",
+            "cardArtCid": "cid",
+            "providerSignature": null,
+        });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Runtime of provider `p1` with `extra` config lines appended.
+    fn runtime(extra: &str) -> KycRuntime {
+        let raw = format!(
+            "walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             providers:\n\
+             - provider_id: p1\n  \
+               public_key: \"0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad\"\n\
+             {}",
+            "00".repeat(144),
+            extra
+        );
+        KycRuntime::try_from(FileKycConfig::parse(&raw).unwrap()).unwrap()
+    }
+
+    /// State with `runtime` loaded and its Walrus aggregator served locally,
+    /// returning `DOCUMENT` for every blob.
+    async fn state(mut runtime: KycRuntime) -> Arc<AppState> {
+        let aggregator = Router::new().route("/v1/blobs/:blob_id", get(|| async { DOCUMENT }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        runtime.walrus_aggregator_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, aggregator).await.unwrap() });
+        Arc::new(AppState {
+            keys: RwLock::new(KeyRing::new(EnclaveKey::generate(0))),
+            attestor: Box::new(NitroAttestor),
+            measurement: None,
+            walrus_client: Client::new(),
+            kyc_runtime: RwLock::new(Some(runtime)),
+            kyc_jobs: Default::default(),
+            rate_limiter: Default::default(),
+        })
+    }
+
+    async fn batch(
+        state: &Arc<AppState>,
+        payloads: Vec<KycRequestPayload>,
+    ) -> Result<ProcessDataBatchResponse, EnclaveError> {
+        let body = Ok(Json(ProcessDataRequest { payload: payloads }));
+        process_data_batch(State(state.clone()), body)
+            .await
+            .map(|Json(response)| response)
+    }
+
+    fn unsigned_payload(blob_id: &str, extra: serde_json::Value) -> KycRequestPayload {
+        let mut fields = json!({ "blobId": blob_id, "docHash": doc_hash(DOCUMENT) });
+        fields
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        payload(fields)
+    }
+
+    #[tokio::test]
+    async fn batch_size_is_bounded() {
+        let state = state(runtime("  allow_unsigned: true\n")).await;
+        for size in [0, MAX_BATCH_SIZE + 1] {
+            let payloads = (0..size)
+                .map(|_| unsigned_payload("blob", json!({})))
+                .collect();
+            let Err(EnclaveError::InvalidRequest(violations)) = batch(&state, payloads).await
+            else {
+                panic!("a batch of {} payloads should be rejected", size);
+            };
+            assert_eq!(violations[0].field, "payload");
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_reports_each_entry_in_order() {
+        let state = state(runtime("  allow_unsigned: true\n")).await;
+        let payloads = vec![
+            unsigned_payload("blob-0", json!({})),
+            unsigned_payload("blob-1", json!({ "kycLevel": 9, "userWallet": "alice" })),
+            unsigned_payload(
+                "blob-2",
+                json!({ "docHash": format!("0x{}", "00".repeat(32)) }),
+            ),
+            unsigned_payload("blob-3", json!({ "providerId": "unknown" })),
+            unsigned_payload("blob-4", json!({ "kycLevel": 1 })),
+        ];
+        let results = batch(&state, payloads).await.unwrap().results;
+        assert_eq!(
+            results.iter().map(|item| item.index).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+
+        for (index, level) in [(0, 2), (4, 1)] {
+            let Some(KycCredential::Plain(signed)) = &results[index].response else {
+                panic!("entry {} should succeed: {:?}", index, results[index].error);
+            };
+            assert_eq!(signed.response.data.blobId, format!("blob-{}", index));
+            assert_eq!(signed.response.data.kycLevel, level);
+            assert!(results[index].error.is_none());
+        }
+
+        let error = results[1].error.as_ref().unwrap();
+        assert_eq!(error.error, "Invalid request");
+        let fields: Vec<&str> = error.violations.iter().map(|v| v.field.as_str()).collect();
+        assert_eq!(fields, ["userWallet", "kycLevel"]);
+        assert!(results[2]
+            .error
+            .as_ref()
+            .unwrap()
+            .error
+            .contains("doc_hash mismatch"));
+        assert_eq!(
+            results[3].error.as_ref().unwrap().error,
+            "Unknown provider unknown"
+        );
+        assert!(results[1..4].iter().all(|item| item.response.is_none()));
+    }
+
+    fn screen(runtime: &KycRuntime, extra: serde_json::Value) -> ScreeningOutcome {
+        screen_applicant(runtime, &runtime.providers[0], &payload(extra), "DE").unwrap()
+    }
+
+    #[test]
+    fn sanctions_result_is_the_enclaves_own() {
+        let unscreened = screen(&runtime(""), json!({}));
+        assert!(!unscreened.is_sanctioned);
+        assert_eq!(unscreened.sanctions_list_version, None);
+
+        let runtime = runtime(
+            "sanctions_list:\n  \
+               version: sdn-1\n  \
+               format: csv\n  \
+               data: \"name,aliases,date_of_birth,nationality\\nJane Roe,,,\\n\"\n",
+        );
+        let clear = screen(&runtime, json!({ "fullName": "John Doe" }));
+        assert!(!clear.is_sanctioned);
+        assert_eq!(clear.sanctions_list_version.as_deref(), Some("sdn-1"));
+        assert!(screen(&runtime, json!({ "fullName": "Jane Roe" })).is_sanctioned);
+    }
+
+    #[test]
+    fn pep_result_follows_match_confidence() {
+        let unscreened = screen(&runtime(""), json!({}));
+        assert!(!unscreened.is_pep);
+        assert_eq!(unscreened.pep_match_confidence, PepMatchConfidence::None);
+        assert_eq!(unscreened.pep_list_version, None);
+
+        let runtime = runtime(
+            "pep_list:\n  \
+               version: pep-1\n  \
+               format: json\n  \
+               data: '[{\"name\": \"Alexander Ivanov\"}]'\n",
+        );
+        let clear = screen(&runtime, json!({ "fullName": "Jane Roe" }));
+        assert!(!clear.is_pep);
+        assert_eq!(clear.pep_match_confidence, PepMatchConfidence::None);
+        assert_eq!(clear.pep_list_version.as_deref(), Some("pep-1"));
+        let hit = screen(&runtime, json!({ "fullName": "Alexander Ivanov" }));
+        assert!(hit.is_pep);
+        assert_eq!(hit.pep_match_confidence, PepMatchConfidence::High);
+    }
+
+    const ISSUED_AT_MS: u64 = 1_700_000_000_000;
+    const LEVEL_2_VALIDITY_MS: u64 = 1_000_000;
+
+    fn expiry(extra: serde_json::Value) -> Result<u64, EnclaveError> {
+        let runtime = runtime("level_validity_ms:\n  2: 1000000\n");
+        credential_expiry(
+            &runtime,
+            &runtime.providers[0],
+            &payload(extra),
+            ISSUED_AT_MS,
+        )
+    }
+
+    #[test]
+    fn expiry_defaults_to_the_level_validity() {
+        assert_eq!(
+            expiry(json!({})).unwrap(),
+            ISSUED_AT_MS + LEVEL_2_VALIDITY_MS
+        );
+        let later = ISSUED_AT_MS + 2 * LEVEL_2_VALIDITY_MS;
+        assert_eq!(
+            expiry(json!({ "documentExpiresAtMs": later })).unwrap(),
+            ISSUED_AT_MS + LEVEL_2_VALIDITY_MS
+        );
+    }
+
+    #[test]
+    fn expiry_is_capped_by_the_document() {
+        let sooner = ISSUED_AT_MS + LEVEL_2_VALIDITY_MS / 2;
+        assert_eq!(
+            expiry(json!({ "documentExpiresAtMs": sooner })).unwrap(),
+            sooner
+        );
+    }
+
+    #[test]
+    fn expired_documents_are_refused() {
+        for expired in [ISSUED_AT_MS, ISSUED_AT_MS - 1, 0] {
+            let error = expiry(json!({ "documentExpiresAtMs": expired })).unwrap_err();
+            assert!(
+                error.to_string().contains("Identity document expired"),
+                "{}",
+                error
+            );
+        }
+    }
+
+    fn message(extra: serde_json::Value) -> Vec<u8> {
+        canonical_message(&payload(extra))
+    }
+
+    #[test]
+    fn canonical_message_layout() {
+        let mut expected = PROVIDER_MESSAGE_PREFIX.to_vec();
+        for field in ["p1", "0x1", "blob", "0xab"] {
+            expected.push(field.len() as u8);
+            expected.extend(field.as_bytes());
+        }
+        expected.extend([2, 2, b'D', b'E']);
+        // documentExpiresAtMs, fullName, dateOfBirth and residenceCountry unset.
+        expected.extend([0, 0, 0, 0]);
+        assert_eq!(message(json!({})), expected);
+
+        let with_dob = message(json!({ "dateOfBirth": "2000-01-01" }));
+        assert_eq!(
+            &with_dob[..expected.len() - 2],
+            &expected[..expected.len() - 2]
+        );
+        assert_eq!(&with_dob[expected.len() - 2..], b"\x01\x0a2000-01-01\x00");
+    }
+
+    #[test]
+    fn canonical_message_signs_every_field() {
+        let base = message(json!({}));
+        for extra in [
+            json!({ "nationality": "FR" }),
+            json!({ "documentExpiresAtMs": 1 }),
+            json!({ "fullName": "" }),
+            json!({ "dateOfBirth": "2000-01-01" }),
+            json!({ "residenceCountry": "DE" }),
+        ] {
+            assert_ne!(message(extra), base);
+        }
+    }
+
+    #[test]
+    fn canonical_message_keeps_free_text_in_its_field() {
+        assert_ne!(
+            message(json!({ "fullName": "Ada Lovelace::dob:1815-12-10" })),
+            message(json!({ "fullName": "Ada Lovelace", "dateOfBirth": "1815-12-10" }))
+        );
+        assert_ne!(
+            message(json!({ "blobId": "blob::0xab", "docHash": "" })),
+            message(json!({}))
+        );
+    }
+
+    fn commit_violations(fields: serde_json::Value) -> Vec<String> {
+        match validation::validate_payload(&payload(json!({ "commitFields": fields }))) {
+            Err(EnclaveError::InvalidRequest(violations)) => violations
+                .into_iter()
+                .filter(|v| v.field == "commitFields")
+                .map(|v| v.message)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn committing_is_pep_requires_committing_pep_match_confidence() {
+        assert_eq!(commit_violations(json!(["isPep"])).len(), 1);
+        assert!(commit_violations(json!(["isPep", "pepMatchConfidence"])).is_empty());
+        assert!(commit_violations(json!(["pepMatchConfidence"])).is_empty());
+    }
 }
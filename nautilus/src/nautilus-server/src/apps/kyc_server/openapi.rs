@@ -0,0 +1,57 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{
+    CredentialFormat, DenialReason, KycCommittedResponsePayload, KycDenialPayload, KycJobEvent,
+    KycResponsePayload,
+};
+use axum::Json;
+use utoipa::OpenApi;
+
+/// OpenAPI document of the enclave API, generated from the handlers and payload
+/// types. `host` endpoints are served on port 3001 to the parent instance only.
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::common::get_attestation,
+        crate::common::health_check,
+        crate::common::get_keys,
+        super::process_data,
+        super::process_data_batch,
+        super::jobs::process_data_async,
+        super::jobs::get_job,
+        super::revocation::revoke_kyc,
+        openapi_json,
+        super::rate_limit::metrics,
+        crate::common::rotate_key,
+        super::blocklist::block_wallets,
+        super::blocklist::unblock_wallets,
+        super::bootstrap::init_parameter_load,
+        super::bootstrap::complete_parameter_load,
+    ),
+    components(schemas(
+        CredentialFormat,
+        KycResponsePayload,
+        KycCommittedResponsePayload,
+        KycJobEvent,
+        KycDenialPayload,
+        DenialReason,
+    )),
+    tags(
+        (name = "kyc", description = "KYC issuance and revocation"),
+        (name = "enclave", description = "Enclave keys, attestation and health"),
+        (name = "host", description = "Host-only endpoints on port 3001"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Endpoint that serves this OpenAPI document.
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "enclave",
+    responses((status = 200, description = "OpenAPI 3.1 document"))
+)]
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,245 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{normalize_country, KycRequestPayload};
+use crate::{EnclaveError, FieldViolation};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use utoipa::ToSchema;
+
+/// A boolean claim the enclave derives from document data it never releases.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredicateConfig {
+    /// Name dApps see in the signed response, e.g. `over_18`.
+    pub name: String,
+    #[serde(flatten)]
+    pub rule: PredicateRule,
+}
+
+// `deny_unknown_fields` goes on the rule: `PredicateConfig` flattens it, which
+// rules the attribute out there, and the rule sees every key but `name`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PredicateRule {
+    /// Applicant is at least `years` old on the day of issuance.
+    AgeAtLeast {
+        years: u32,
+    },
+    NationalityIn {
+        countries: Vec<String>,
+    },
+    NationalityNotIn {
+        countries: Vec<String>,
+    },
+    ResidenceIn {
+        countries: Vec<String>,
+    },
+    ResidenceNotIn {
+        countries: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PredicateResult {
+    pub name: String,
+    pub value: bool,
+}
+
+/// Normalizes country lists to alpha-2, reporting duplicate predicate names and
+/// unrecognized countries into `violations`.
+pub(crate) fn load_predicates(
+    predicates: &[PredicateConfig],
+    violations: &mut Vec<FieldViolation>,
+) -> Vec<PredicateConfig> {
+    let mut names = BTreeSet::new();
+    let mut loaded = Vec::with_capacity(predicates.len());
+    for (index, predicate) in predicates.iter().enumerate() {
+        if !names.insert(predicate.name.as_str()) {
+            violations.push(FieldViolation::new(
+                &format!("predicates[{}].name", index),
+                format!("{} is defined twice", predicate.name),
+            ));
+        }
+        let mut normalize = |countries: &[String]| {
+            countries
+                .iter()
+                .enumerate()
+                .filter_map(|(position, country)| match normalize_country(country) {
+                    Some(code) => Some(code.to_string()),
+                    None => {
+                        violations.push(FieldViolation::new(
+                            &format!("predicates[{}].countries[{}]", index, position),
+                            format!("unrecognized country {}", country),
+                        ));
+                        None
+                    }
+                })
+                .collect()
+        };
+        let rule = match &predicate.rule {
+            PredicateRule::AgeAtLeast { years } => PredicateRule::AgeAtLeast { years: *years },
+            PredicateRule::NationalityIn { countries } => PredicateRule::NationalityIn {
+                countries: normalize(countries),
+            },
+            PredicateRule::NationalityNotIn { countries } => PredicateRule::NationalityNotIn {
+                countries: normalize(countries),
+            },
+            PredicateRule::ResidenceIn { countries } => PredicateRule::ResidenceIn {
+                countries: normalize(countries),
+            },
+            PredicateRule::ResidenceNotIn { countries } => PredicateRule::ResidenceNotIn {
+                countries: normalize(countries),
+            },
+        };
+        loaded.push(PredicateConfig {
+            name: predicate.name.clone(),
+            rule,
+        });
+    }
+    loaded
+}
+
+/// Evaluates the predicates named in `requestedPredicates`, in request order.
+pub(crate) fn evaluate_predicates(
+    predicates: &[PredicateConfig],
+    payload: &KycRequestPayload,
+    nationality: &str,
+    now_ms: u64,
+) -> Result<Vec<PredicateResult>, EnclaveError> {
+    let Some(requested) = &payload.requestedPredicates else {
+        return Ok(Vec::new());
+    };
+    let missing = |predicate: &str, field: &str| {
+        EnclaveError::GenericError(format!("Predicate {} requires {}", predicate, field))
+    };
+    requested
+        .iter()
+        .map(|name| {
+            let predicate = predicates
+                .iter()
+                .find(|predicate| &predicate.name == name)
+                .ok_or_else(|| EnclaveError::GenericError(format!("Unknown predicate {}", name)))?;
+            let residence = || {
+                payload
+                    .residenceCountry
+                    .as_deref()
+                    .and_then(normalize_country)
+                    .ok_or_else(|| missing(name, "residenceCountry"))
+            };
+            let value = match &predicate.rule {
+                PredicateRule::AgeAtLeast { years } => {
+                    let dob = payload
+                        .dateOfBirth
+                        .as_deref()
+                        .and_then(parse_date)
+                        .ok_or_else(|| missing(name, "dateOfBirth"))?;
+                    age_on(dob, date_from_ms(now_ms)) >= *years
+                }
+                PredicateRule::NationalityIn { countries } => {
+                    countries.iter().any(|country| country == nationality)
+                }
+                PredicateRule::NationalityNotIn { countries } => {
+                    !countries.iter().any(|country| country == nationality)
+                }
+                PredicateRule::ResidenceIn { countries } => {
+                    let residence = residence()?;
+                    countries.iter().any(|country| country == residence)
+                }
+                PredicateRule::ResidenceNotIn { countries } => {
+                    let residence = residence()?;
+                    !countries.iter().any(|country| country == residence)
+                }
+            };
+            Ok(PredicateResult {
+                name: name.clone(),
+                value,
+            })
+        })
+        .collect()
+}
+
+/// (year, month, day) of a `YYYY-MM-DD` string.
+fn parse_date(value: &str) -> Option<(i64, u32, u32)> {
+    let mut parts = value.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    Some((year, month, day))
+}
+
+/// UTC civil date of a unix timestamp in milliseconds.
+pub(crate) fn date_from_ms(ms: u64) -> (i64, u32, u32) {
+    // Howard Hinnant's civil_from_days.
+    let z = (ms / 86_400_000) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Completed years between `birth` and `today`.
+fn age_on(birth: (i64, u32, u32), today: (i64, u32, u32)) -> u32 {
+    let mut age = today.0 - birth.0;
+    if (today.1, today.2) < (birth.1, birth.2) {
+        age -= 1;
+    }
+    age.max(0) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_MS: u64 = 86_400_000;
+
+    #[test]
+    fn date_from_ms_converts_to_utc_dates() {
+        assert_eq!(date_from_ms(0), (1970, 1, 1));
+        assert_eq!(date_from_ms(DAY_MS - 1), (1970, 1, 1));
+        assert_eq!(date_from_ms(DAY_MS), (1970, 1, 2));
+        assert_eq!(date_from_ms(951_782_400_000), (2000, 2, 29));
+        assert_eq!(date_from_ms(951_868_800_000), (2000, 3, 1));
+        assert_eq!(date_from_ms(1_709_164_800_000), (2024, 2, 29));
+        assert_eq!(date_from_ms(1_735_689_599_999), (2024, 12, 31));
+    }
+
+    #[test]
+    fn parse_date_reads_iso_dates() {
+        assert_eq!(parse_date("1990-07-15"), Some((1990, 7, 15)));
+        assert_eq!(parse_date("1904-02-29"), Some((1904, 2, 29)));
+        assert_eq!(parse_date("1990-07"), None);
+        assert_eq!(parse_date("15/07/1990"), None);
+        assert_eq!(parse_date("1990-xx-15"), None);
+    }
+
+    #[test]
+    fn age_on_counts_completed_years() {
+        assert_eq!(age_on((2000, 7, 15), (2018, 7, 14)), 17);
+        assert_eq!(age_on((2000, 7, 15), (2018, 7, 15)), 18);
+        assert_eq!(age_on((2000, 7, 15), (2018, 12, 31)), 18);
+        assert_eq!(age_on((2030, 1, 1), (2024, 1, 1)), 0);
+    }
+
+    #[test]
+    fn age_on_handles_leap_day_births() {
+        // Born on 29 February: a year is completed on 1 March in common years.
+        assert_eq!(age_on((2004, 2, 29), (2022, 2, 28)), 17);
+        assert_eq!(age_on((2004, 2, 29), (2022, 3, 1)), 18);
+        assert_eq!(age_on((2004, 2, 29), (2024, 2, 29)), 20);
+    }
+
+    #[test]
+    fn age_on_handles_births_before_1970() {
+        assert_eq!(age_on((1950, 6, 15), date_from_ms(0)), 19);
+        assert_eq!(age_on((1899, 12, 31), (2000, 1, 1)), 100);
+        let today = date_from_ms(1_718_409_600_000);
+        assert_eq!(today, (2024, 6, 15));
+        assert_eq!(age_on((1950, 6, 15), today), 74);
+        assert_eq!(age_on((1950, 6, 16), today), 73);
+    }
+}
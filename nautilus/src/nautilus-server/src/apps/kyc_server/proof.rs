@@ -0,0 +1,167 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{
+    KycCommittedResponsePayload, KycDenialPayload, KycResponsePayload, KycRevocationPayload,
+};
+use crate::common::{
+    verify_signed_response, IntentMessage, IntentScope, KeyHandover, ProcessedDataResponse,
+    ProofError, TimestampBounds,
+};
+use fastcrypto::ed25519::Ed25519PublicKey;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A message signed by the enclave under any of the scopes the KYC app uses.
+#[derive(Debug, Clone)]
+pub enum KycProof {
+    ProcessData(ProcessedDataResponse<IntentMessage<KycResponsePayload>>),
+    KeyRotation(ProcessedDataResponse<IntentMessage<KeyHandover>>),
+    Revocation(ProcessedDataResponse<IntentMessage<KycRevocationPayload>>),
+    Denial(ProcessedDataResponse<IntentMessage<KycDenialPayload>>),
+    CommittedData(ProcessedDataResponse<IntentMessage<KycCommittedResponsePayload>>),
+}
+
+/// BCS proof layout: the signed intent message followed by the signature bytes.
+#[derive(Serialize, Deserialize)]
+struct BcsProof<T: Serialize> {
+    response: IntentMessage<T>,
+    signature: Vec<u8>,
+}
+
+fn malformed(what: impl Into<String>) -> ProofError {
+    ProofError::Malformed(what.into())
+}
+
+fn from_json_value<T: DeserializeOwned>(value: Value) -> Result<T, ProofError> {
+    serde_json::from_value(value).map_err(|e| malformed(e.to_string()))
+}
+
+/// Decodes `bytes` as the BCS intent message alone when `signature` is given,
+/// otherwise as a `BcsProof` carrying its own signature.
+fn from_bcs<T: Serialize + DeserializeOwned>(
+    bytes: &[u8],
+    signature: Option<&str>,
+) -> Result<ProcessedDataResponse<IntentMessage<T>>, ProofError> {
+    let invalid = |e: bcs::Error| malformed(format!("invalid BCS: {}", e));
+    Ok(match signature {
+        Some(signature) => ProcessedDataResponse {
+            response: bcs::from_bytes(bytes).map_err(invalid)?,
+            signature: signature.trim().trim_start_matches("0x").to_string(),
+        },
+        None => {
+            let proof: BcsProof<T> = bcs::from_bytes(bytes).map_err(invalid)?;
+            ProcessedDataResponse {
+                response: proof.response,
+                signature: Hex::encode(proof.signature),
+            }
+        }
+    })
+}
+
+impl KycProof {
+    /// Parses a proof as JSON when the input looks like JSON, otherwise as BCS hex.
+    pub fn parse(input: &str, signature: Option<&str>) -> Result<Self, ProofError> {
+        let input = input.trim();
+        if input.starts_with('{') {
+            Self::from_json(input)
+        } else {
+            Self::from_bcs_hex(input, signature)
+        }
+    }
+
+    /// Parses the JSON returned by the enclave: a `ProcessedDataResponse`, a
+    /// committed credential (`signed` plus `openings`) or an error body carrying a
+    /// signed `denial`.
+    pub fn from_json(input: &str) -> Result<Self, ProofError> {
+        let mut value: Value = serde_json::from_str(input).map_err(|e| malformed(e.to_string()))?;
+        if value.get("signed").is_some() {
+            value = value["signed"].take();
+        } else if value.get("denial").is_some_and(|denial| !denial.is_null()) {
+            value = value["denial"].take();
+        }
+        let scope: IntentScope = from_json_value(value["response"]["intent"].clone())
+            .map_err(|_| malformed("response.intent is not a known intent scope"))?;
+        Ok(match scope {
+            IntentScope::ProcessData => KycProof::ProcessData(from_json_value(value)?),
+            IntentScope::KeyRotation => KycProof::KeyRotation(from_json_value(value)?),
+            IntentScope::Revocation => KycProof::Revocation(from_json_value(value)?),
+            IntentScope::Denial => KycProof::Denial(from_json_value(value)?),
+            IntentScope::CommittedData => KycProof::CommittedData(from_json_value(value)?),
+        })
+    }
+
+    /// Parses hex encoded BCS: the intent message exactly as signed by
+    /// `to_signed_response`, followed by the BCS `vector<u8>` signature unless
+    /// `signature` is passed separately as hex.
+    pub fn from_bcs_hex(input: &str, signature: Option<&str>) -> Result<Self, ProofError> {
+        let bytes = Hex::decode(input.trim().trim_start_matches("0x"))
+            .map_err(|_| malformed("proof is neither JSON nor hex"))?;
+        let scope: IntentScope = bytes
+            .first()
+            .and_then(|intent| bcs::from_bytes(&[*intent]).ok())
+            .ok_or_else(|| malformed("first byte is not a known intent scope"))?;
+        Ok(match scope {
+            IntentScope::ProcessData => KycProof::ProcessData(from_bcs(&bytes, signature)?),
+            IntentScope::KeyRotation => KycProof::KeyRotation(from_bcs(&bytes, signature)?),
+            IntentScope::Revocation => KycProof::Revocation(from_bcs(&bytes, signature)?),
+            IntentScope::Denial => KycProof::Denial(from_bcs(&bytes, signature)?),
+            IntentScope::CommittedData => KycProof::CommittedData(from_bcs(&bytes, signature)?),
+        })
+    }
+
+    pub fn scope(&self) -> IntentScope {
+        match self {
+            KycProof::ProcessData(_) => IntentScope::ProcessData,
+            KycProof::KeyRotation(_) => IntentScope::KeyRotation,
+            KycProof::Revocation(_) => IntentScope::Revocation,
+            KycProof::Denial(_) => IntentScope::Denial,
+            KycProof::CommittedData(_) => IntentScope::CommittedData,
+        }
+    }
+
+    /// Checks the signature against `public_key`, the scope (when `expected_scope`
+    /// is given) and the signed timestamp.
+    pub fn verify(
+        &self,
+        public_key: &Ed25519PublicKey,
+        expected_scope: Option<IntentScope>,
+        bounds: &TimestampBounds,
+    ) -> Result<(), ProofError> {
+        let scope = expected_scope.unwrap_or_else(|| self.scope());
+        match self {
+            KycProof::ProcessData(signed) => {
+                verify_signed_response(public_key, signed, scope, bounds)
+            }
+            KycProof::KeyRotation(signed) => {
+                verify_signed_response(public_key, signed, scope, bounds)
+            }
+            KycProof::Revocation(signed) => {
+                verify_signed_response(public_key, signed, scope, bounds)
+            }
+            KycProof::Denial(signed) => verify_signed_response(public_key, signed, scope, bounds),
+            KycProof::CommittedData(signed) => {
+                verify_signed_response(public_key, signed, scope, bounds)
+            }
+        }
+    }
+
+    /// Readable form: scope name, timestamp, decoded payload and hex signature.
+    pub fn to_json(&self) -> Value {
+        let signed = match self {
+            KycProof::ProcessData(signed) => json!(signed),
+            KycProof::KeyRotation(signed) => json!(signed),
+            KycProof::Revocation(signed) => json!(signed),
+            KycProof::Denial(signed) => json!(signed),
+            KycProof::CommittedData(signed) => json!(signed),
+        };
+        json!({
+            "scope": format!("{:?}", self.scope()),
+            "timestamp_ms": signed["response"]["timestamp_ms"],
+            "data": signed["response"]["data"],
+            "signature": signed["signature"],
+        })
+    }
+}
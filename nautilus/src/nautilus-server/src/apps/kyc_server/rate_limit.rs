@@ -0,0 +1,435 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{normalize_sui_address, KycRequestPayload, KycRuntime, ProviderRuntime};
+use crate::AppState;
+use crate::EnclaveError;
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Wallet buckets kept at most; reaching it triggers an eviction.
+const MAX_TRACKED_WALLETS: usize = 100_000;
+/// Wallet buckets left after an eviction, so the next one is far off.
+const EVICTED_TRACKED_WALLETS: usize = MAX_TRACKED_WALLETS * 9 / 10;
+
+/// Token bucket settings: `requests_per_minute` sustained, bursts of up to `burst`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: u32,
+    /// Bucket capacity; `requests_per_minute` when unset.
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+impl RateLimitConfig {
+    fn capacity(&self) -> f64 {
+        f64::from(self.burst.unwrap_or(self.requests_per_minute))
+    }
+
+    fn tokens_per_sec(&self) -> f64 {
+        f64::from(self.requests_per_minute) / 60.0
+    }
+}
+
+/// Which limit rejected a request, as reported in metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum LimitKind {
+    Concurrency,
+    Provider,
+    Wallet,
+}
+
+impl LimitKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            LimitKind::Concurrency => "concurrency",
+            LimitKind::Provider => "provider",
+            LimitKind::Wallet => "wallet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(limit: &RateLimitConfig, now: Instant) -> Self {
+        TokenBucket {
+            tokens: limit.capacity(),
+            updated: now,
+        }
+    }
+
+    /// Adds the tokens earned since the last update, capped at the capacity.
+    fn refill(&mut self, limit: &RateLimitConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.tokens_per_sec()).min(limit.capacity());
+        self.updated = now;
+    }
+
+    /// Whether the bucket would be full at `now`, i.e. identical to a new one.
+    fn is_full_at(&self, limit: &RateLimitConfig, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * limit.tokens_per_sec() >= limit.capacity()
+    }
+
+    /// Time until a token is available; zero if one is available now.
+    fn wait(&self, limit: &RateLimitConfig) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / limit.tokens_per_sec())
+        }
+    }
+}
+
+/// Releases its slot of the global concurrency cap when dropped.
+pub struct ConcurrencyPermit {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Rate limit state. Buckets are kept across config reloads; the limits
+/// themselves are read from the runtime on every request.
+#[derive(Default)]
+pub struct RateLimiter {
+    providers: Mutex<HashMap<String, TokenBucket>>,
+    wallets: Mutex<HashMap<String, TokenBucket>>,
+    in_flight: Arc<AtomicUsize>,
+    /// Rejections by limit and provider id.
+    rejections: Mutex<BTreeMap<(LimitKind, String), u64>>,
+}
+
+fn rate_limited(message: String, wait: Duration) -> EnclaveError {
+    EnclaveError::RateLimited {
+        message,
+        retry_after_secs: wait.as_secs_f64().ceil().max(1.0) as u64,
+    }
+}
+
+impl RateLimiter {
+    fn record(&self, kind: LimitKind, provider_id: &str) {
+        *self
+            .rejections
+            .lock()
+            .expect("rate limiter lock poisoned")
+            .entry((kind, provider_id.to_string()))
+            .or_default() += 1;
+    }
+
+    /// Takes a slot of `max_concurrent_requests`, if configured.
+    pub fn acquire(&self, runtime: &KycRuntime) -> Result<ConcurrencyPermit, EnclaveError> {
+        let previous = self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let permit = ConcurrencyPermit {
+            in_flight: self.in_flight.clone(),
+        };
+        match runtime.max_concurrent_requests {
+            Some(max) if previous >= max => {
+                self.record(LimitKind::Concurrency, "");
+                Err(rate_limited(
+                    format!("Enclave is at its limit of {} concurrent requests", max),
+                    Duration::from_secs(1),
+                ))
+            }
+            _ => Ok(permit),
+        }
+    }
+
+    /// Takes one token from the provider's bucket and one from the wallet's.
+    /// Neither is taken unless both are available.
+    pub fn check(
+        &self,
+        runtime: &KycRuntime,
+        provider: &ProviderRuntime,
+        payload: &KycRequestPayload,
+    ) -> Result<(), EnclaveError> {
+        let now = Instant::now();
+        let mut providers = self.providers.lock().expect("rate limiter lock poisoned");
+        let mut wallets = self.wallets.lock().expect("rate limiter lock poisoned");
+
+        let provider_bucket = provider.rate_limit.as_ref().map(|limit| {
+            let bucket = providers
+                .entry(provider.provider_id.clone())
+                .or_insert_with(|| TokenBucket::full(limit, now));
+            bucket.refill(limit, now);
+            (limit, bucket)
+        });
+        if let Some((limit, bucket)) = &provider_bucket {
+            let wait = bucket.wait(limit);
+            if !wait.is_zero() {
+                self.record(LimitKind::Provider, &provider.provider_id);
+                return Err(rate_limited(
+                    format!(
+                        "Provider {} exceeded {} requests per minute",
+                        provider.provider_id, limit.requests_per_minute
+                    ),
+                    wait,
+                ));
+            }
+        }
+
+        if let Some(limit) = &runtime.wallet_rate_limit {
+            let wallet = normalize_sui_address(&payload.userWallet)
+                .unwrap_or_else(|| payload.userWallet.clone());
+            if wallets.len() >= MAX_TRACKED_WALLETS && !wallets.contains_key(&wallet) {
+                evict_wallets(&mut wallets, limit, now);
+            }
+            let bucket = wallets
+                .entry(wallet)
+                .or_insert_with(|| TokenBucket::full(limit, now));
+            bucket.refill(limit, now);
+            let wait = bucket.wait(limit);
+            if !wait.is_zero() {
+                self.record(LimitKind::Wallet, &provider.provider_id);
+                return Err(rate_limited(
+                    format!(
+                        "userWallet exceeded {} requests per minute",
+                        limit.requests_per_minute
+                    ),
+                    wait,
+                ));
+            }
+            bucket.tokens -= 1.0;
+        }
+        if let Some((_, bucket)) = provider_bucket {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    /// Prometheus text exposition of the rate limit counters.
+    pub fn render_metrics(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP kyc_requests_in_flight Requests holding a concurrency slot.\n");
+        out.push_str("# TYPE kyc_requests_in_flight gauge\n");
+        let _ = writeln!(
+            out,
+            "kyc_requests_in_flight {}",
+            self.in_flight.load(Ordering::SeqCst)
+        );
+        out.push_str("# HELP kyc_rate_limited_total Requests rejected with 429, by limit.\n");
+        out.push_str("# TYPE kyc_rate_limited_total counter\n");
+        let rejections = self.rejections.lock().expect("rate limiter lock poisoned");
+        for ((kind, provider_id), count) in rejections.iter() {
+            if provider_id.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "kyc_rate_limited_total{{limit=\"{}\"}} {}",
+                    kind.as_str(),
+                    count
+                );
+            } else {
+                let _ = writeln!(
+                    out,
+                    "kyc_rate_limited_total{{limit=\"{}\",provider=\"{}\"}} {}",
+                    kind.as_str(),
+                    provider_id.replace('\\', "\\\\").replace('"', "\\\""),
+                    count
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Shrinks the wallet buckets to `EVICTED_TRACKED_WALLETS`. Full buckets go
+/// first, since they behave exactly like new ones; then the least recently used.
+fn evict_wallets(
+    wallets: &mut HashMap<String, TokenBucket>,
+    limit: &RateLimitConfig,
+    now: Instant,
+) {
+    wallets.retain(|_, bucket| !bucket.is_full_at(limit, now));
+    if wallets.len() <= EVICTED_TRACKED_WALLETS {
+        return;
+    }
+    let mut by_age: Vec<(Instant, String)> = wallets
+        .iter()
+        .map(|(wallet, bucket)| (bucket.updated, wallet.clone()))
+        .collect();
+    by_age.sort_unstable();
+    for (_, wallet) in by_age.iter().take(wallets.len() - EVICTED_TRACKED_WALLETS) {
+        wallets.remove(wallet);
+    }
+}
+
+/// Endpoint that exposes rate limit metrics in the Prometheus text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "enclave",
+    responses((status = 200, description = "Prometheus text exposition", content_type = "text/plain"))
+)]
+pub async fn metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.rate_limiter.render_metrics(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::FileKycConfig;
+    use serde_json::json;
+
+    const LIMIT: RateLimitConfig = RateLimitConfig {
+        requests_per_minute: 60,
+        burst: Some(2),
+    };
+
+    fn runtime() -> KycRuntime {
+        let raw = format!(
+            "walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             providers:\n\
+             - provider_id: p1\n  \
+               public_key: \"0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad\"\n  \
+               rate_limit: {{requests_per_minute: 60, burst: 2}}\n\
+             wallet_rate_limit: {{requests_per_minute: 60, burst: 1}}\n",
+            "00".repeat(144)
+        );
+        KycRuntime::try_from(FileKycConfig::parse(&raw).unwrap()).unwrap()
+    }
+
+    fn payload(wallet: &str) -> KycRequestPayload {
+        serde_json::from_value(json!({
+            "userWallet": wallet,
+            "providerId": "p1",
+            "kycLevel": 2,
+            "blobId": "blob",
+            "docHash": "0xab",
+            "walrusBlobObject": null,
+            "nationality": "DE",
+            "cardArtCid": "cid",
+            "providerSignature": null,
+        }))
+        .unwrap()
+    }
+
+    fn check(limiter: &RateLimiter, runtime: &KycRuntime, wallet: &str) -> Option<LimitKind> {
+        let before = limiter.rejections.lock().unwrap().clone();
+        match limiter.check(runtime, &runtime.providers[0], &payload(wallet)) {
+            Ok(()) => None,
+            Err(EnclaveError::RateLimited {
+                retry_after_secs, ..
+            }) => {
+                assert!(retry_after_secs >= 1);
+                let after = limiter.rejections.lock().unwrap().clone();
+                after
+                    .into_iter()
+                    .find(|(key, count)| before.get(key) != Some(count))
+                    .map(|((kind, _), _)| kind)
+            }
+            Err(e) => panic!("unexpected error {}", e),
+        }
+    }
+
+    fn wallet_tokens(limiter: &RateLimiter, wallet: &str) -> Option<f64> {
+        let wallet = normalize_sui_address(wallet).unwrap();
+        limiter
+            .wallets
+            .lock()
+            .unwrap()
+            .get(&wallet)
+            .map(|bucket| bucket.tokens)
+    }
+
+    #[test]
+    fn token_bucket_refills_up_to_capacity() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(&LIMIT, start);
+        assert_eq!(bucket.tokens, 2.0);
+        assert_eq!(bucket.wait(&LIMIT), Duration::ZERO);
+
+        bucket.tokens = 0.0;
+        assert_eq!(bucket.wait(&LIMIT), Duration::from_secs(1));
+        bucket.refill(&LIMIT, start + Duration::from_millis(500));
+        assert_eq!(bucket.tokens, 0.5);
+        assert_eq!(bucket.wait(&LIMIT), Duration::from_millis(500));
+        assert!(!bucket.is_full_at(&LIMIT, start + Duration::from_millis(1_900)));
+        assert!(bucket.is_full_at(&LIMIT, start + Duration::from_secs(2)));
+
+        bucket.refill(&LIMIT, start + Duration::from_secs(60));
+        assert_eq!(bucket.tokens, 2.0);
+    }
+
+    #[test]
+    fn check_takes_a_token_from_both_buckets() {
+        let (limiter, runtime) = (RateLimiter::default(), runtime());
+        assert_eq!(check(&limiter, &runtime, "0x1"), None);
+        assert!(wallet_tokens(&limiter, "0x1").unwrap() < 1.0);
+        let provider_tokens = limiter.providers.lock().unwrap()["p1"].tokens;
+        assert!((1.0..2.0).contains(&provider_tokens));
+    }
+
+    #[test]
+    fn wallet_rejection_does_not_debit_the_provider() {
+        let (limiter, runtime) = (RateLimiter::default(), runtime());
+        assert_eq!(check(&limiter, &runtime, "0x1"), None);
+        assert_eq!(check(&limiter, &runtime, "0x1"), Some(LimitKind::Wallet));
+        // The provider bucket still holds the second of its two tokens.
+        assert_eq!(check(&limiter, &runtime, "0x2"), None);
+        assert_eq!(check(&limiter, &runtime, "0x3"), Some(LimitKind::Provider));
+    }
+
+    #[test]
+    fn provider_rejection_does_not_debit_the_wallet() {
+        let (limiter, runtime) = (RateLimiter::default(), runtime());
+        assert_eq!(check(&limiter, &runtime, "0x1"), None);
+        assert_eq!(check(&limiter, &runtime, "0x2"), None);
+        assert_eq!(check(&limiter, &runtime, "0x3"), Some(LimitKind::Provider));
+        assert!(wallet_tokens(&limiter, "0x3").is_none_or(|tokens| tokens >= 1.0));
+    }
+
+    #[test]
+    fn eviction_drops_full_buckets_first() {
+        let now = Instant::now();
+        let mut wallets = HashMap::new();
+        wallets.insert("idle".to_string(), TokenBucket::full(&LIMIT, now));
+        wallets.insert(
+            "busy".to_string(),
+            TokenBucket {
+                tokens: 0.0,
+                updated: now,
+            },
+        );
+        evict_wallets(&mut wallets, &LIMIT, now);
+        assert_eq!(wallets.keys().collect::<Vec<_>>(), ["busy"]);
+    }
+
+    #[test]
+    fn eviction_drops_least_recently_used_buckets() {
+        let start = Instant::now();
+        let mut wallets: HashMap<String, TokenBucket> = (0..MAX_TRACKED_WALLETS)
+            .map(|index| {
+                let bucket = TokenBucket {
+                    tokens: 0.0,
+                    updated: start + Duration::from_micros(index as u64),
+                };
+                (index.to_string(), bucket)
+            })
+            .collect();
+        evict_wallets(&mut wallets, &LIMIT, start + Duration::from_secs(1));
+        assert_eq!(wallets.len(), EVICTED_TRACKED_WALLETS);
+        let evicted = MAX_TRACKED_WALLETS - EVICTED_TRACKED_WALLETS;
+        assert!(!wallets.contains_key(&(evicted - 1).to_string()));
+        assert!(wallets.contains_key(&evicted.to_string()));
+    }
+}
@@ -0,0 +1,259 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{
+    ensure_runtime, normalize_sui_address, validation, verify_provider_message, KycRuntime,
+};
+use crate::common::{
+    current_timestamp_ms, to_signed_response, IntentMessage, IntentScope, ProcessDataRequest,
+    ProcessedDataResponse,
+};
+use crate::AppState;
+use crate::{EnclaveError, ErrorResponse, FieldViolation};
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Why a provider withdrew a previously issued KYC result.
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[repr(u8)]
+pub enum RevocationReason {
+    Fraud = 1,
+    ForgedDocument = 2,
+    IdentityMismatch = 3,
+    SanctionsHit = 4,
+    UserRequest = 5,
+    Other = 255,
+}
+
+/// Provider-signed request to revoke a KYC result. At least one of `badgeId`
+/// and `blobId` must identify what is being revoked.
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KycRevocationRequestPayload {
+    pub userWallet: String,
+    pub providerId: String,
+    pub badgeId: Option<String>,
+    pub blobId: Option<String>,
+    pub reasonCode: RevocationReason,
+    pub providerSignature: String,
+}
+
+/// Revocation signed by the enclave under `IntentScope::Revocation`. Absent
+/// references are encoded as empty strings to keep the BCS layout flat, and
+/// `userWallet` is normalized as in `KycResponsePayload`.
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct KycRevocationPayload {
+    pub userWallet: String,
+    pub providerId: String,
+    pub badgeId: String,
+    pub blobId: String,
+    pub reasonCode: u8,
+    pub teeMeasurement: String,
+}
+
+/// Message behind `providerSignature`, over the fields as sent.
+fn revocation_message(payload: &KycRevocationRequestPayload) -> Vec<u8> {
+    format!(
+        "revoke::{}::{}::{}::{}::{}",
+        payload.providerId,
+        payload.userWallet,
+        payload.badgeId.as_deref().unwrap_or_default(),
+        payload.blobId.as_deref().unwrap_or_default(),
+        payload.reasonCode as u8
+    )
+    .into_bytes()
+}
+
+/// Validates a revocation request and checks its provider signature.
+fn authorize_revocation(
+    runtime: &KycRuntime,
+    payload: &KycRevocationRequestPayload,
+) -> Result<KycRevocationPayload, EnclaveError> {
+    let mut violations = Vec::new();
+    let user_wallet = normalize_sui_address(&payload.userWallet);
+    if user_wallet.is_none() {
+        violations.push(FieldViolation::new(
+            "userWallet",
+            "must be a 0x-prefixed Sui address of at most 64 hex digits",
+        ));
+    }
+    let badge_id = payload.badgeId.clone().unwrap_or_default();
+    let blob_id = payload.blobId.clone().unwrap_or_default();
+    if badge_id.is_empty() && blob_id.is_empty() {
+        violations.push(FieldViolation::new(
+            "badgeId",
+            "a revocation must reference a badgeId or blobId",
+        ));
+    }
+    let Some(user_wallet) = user_wallet.filter(|_| violations.is_empty()) else {
+        return Err(EnclaveError::InvalidRequest(violations));
+    };
+
+    let provider = runtime.find_provider(&payload.providerId).ok_or_else(|| {
+        EnclaveError::GenericError(format!("Unknown provider {}", payload.providerId))
+    })?;
+    verify_provider_message(
+        provider,
+        &payload.providerSignature,
+        &revocation_message(payload),
+    )?;
+    Ok(KycRevocationPayload {
+        userWallet: user_wallet,
+        providerId: provider.provider_id.clone(),
+        badgeId: badge_id,
+        blobId: blob_id,
+        reasonCode: payload.reasonCode as u8,
+        teeMeasurement: runtime.enclave_measurement.clone(),
+    })
+}
+
+/// Endpoint that turns a provider-signed revocation into an enclave-signed one.
+/// Unlike `process_data`, a provider signature is always required here, even for
+/// providers configured with `allow_unsigned`.
+#[utoipa::path(
+    post,
+    path = "/revoke_kyc",
+    tag = "kyc",
+    request_body = ProcessDataRequest<KycRevocationRequestPayload>,
+    responses(
+        (status = 200, body = ProcessedDataResponse<IntentMessage<KycRevocationPayload>>),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn revoke_kyc(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<ProcessDataRequest<KycRevocationRequestPayload>>, JsonRejection>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<KycRevocationPayload>>>, EnclaveError> {
+    let payload = validation::parse_body(body)?.payload;
+    let runtime = ensure_runtime(&state).await?;
+    let revocation = authorize_revocation(&runtime, &payload)?;
+
+    let signing_key = state.signing_key().await;
+    Ok(Json(to_signed_response(
+        &signing_key.keypair,
+        revocation,
+        current_timestamp_ms(),
+        IntentScope::Revocation,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::FileKycConfig;
+    use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PrivateKey};
+    use fastcrypto::encoding::{Encoding, Hex};
+    use fastcrypto::traits::{KeyPair, Signer, ToFromBytes};
+    use serde_json::json;
+
+    fn provider_key() -> Ed25519KeyPair {
+        Ed25519PrivateKey::from_bytes(&[3; 32]).unwrap().into()
+    }
+
+    fn runtime() -> KycRuntime {
+        let raw = format!(
+            "walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             providers:\n\
+             - provider_id: p1\n  \
+               public_key: \"0x{}\"\n  \
+               allow_unsigned: true\n",
+            "00".repeat(144),
+            Hex::encode(provider_key().public().as_bytes())
+        );
+        KycRuntime::try_from(FileKycConfig::parse(&raw).unwrap()).unwrap()
+    }
+
+    fn request(extra: serde_json::Value) -> KycRevocationRequestPayload {
+        let mut value = json!({
+            "userWallet": "0xAB",
+            "providerId": "p1",
+            "badgeId": "0x5",
+            "blobId": null,
+            "reasonCode": 2,
+            "providerSignature": "",
+        });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn signed(
+        key: &Ed25519KeyPair,
+        mut payload: KycRevocationRequestPayload,
+    ) -> KycRevocationRequestPayload {
+        payload.providerSignature = Hex::encode(key.sign(&revocation_message(&payload)));
+        payload
+    }
+
+    #[test]
+    fn revocation_message_covers_every_field() {
+        assert_eq!(
+            revocation_message(&request(json!({}))),
+            b"revoke::p1::0xAB::0x5::::2"
+        );
+        assert_eq!(
+            revocation_message(&request(json!({ "badgeId": null, "blobId": "blob" }))),
+            b"revoke::p1::0xAB::::blob::2"
+        );
+    }
+
+    #[test]
+    fn signed_revocations_carry_the_normalized_wallet() {
+        let revocation =
+            authorize_revocation(&runtime(), &signed(&provider_key(), request(json!({})))).unwrap();
+        assert_eq!(revocation.userWallet, format!("0x{:0>64}", "ab"));
+        assert_eq!(revocation.badgeId, "0x5");
+        assert_eq!(revocation.blobId, "");
+        assert_eq!(
+            revocation.reasonCode,
+            RevocationReason::ForgedDocument as u8
+        );
+    }
+
+    #[test]
+    fn bad_provider_signatures_are_rejected() {
+        let runtime = runtime();
+        let other_key = Ed25519PrivateKey::from_bytes(&[4; 32]).unwrap().into();
+        // Required even though the provider is `allow_unsigned`.
+        for payload in [
+            request(json!({})),
+            signed(&other_key, request(json!({}))),
+            request(json!({ "providerSignature": "zz" })),
+        ] {
+            assert!(matches!(
+                authorize_revocation(&runtime, &payload),
+                Err(EnclaveError::GenericError(_))
+            ));
+        }
+
+        // A signature does not carry over to another reason.
+        let mut changed = signed(&provider_key(), request(json!({})));
+        changed.reasonCode = RevocationReason::Fraud;
+        assert!(authorize_revocation(&runtime, &changed).is_err());
+    }
+
+    #[test]
+    fn invalid_requests_list_every_violation() {
+        let payload = signed(
+            &provider_key(),
+            request(json!({ "userWallet": "alice", "badgeId": null })),
+        );
+        let Err(EnclaveError::InvalidRequest(violations)) =
+            authorize_revocation(&runtime(), &payload)
+        else {
+            panic!("expected InvalidRequest");
+        };
+        let fields: Vec<&str> = violations.iter().map(|v| v.field.as_str()).collect();
+        assert_eq!(fields, ["userWallet", "badgeId"]);
+    }
+}
@@ -0,0 +1,367 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{normalize_country, PepListConfig, SanctionsListConfig, ScreeningListFormat};
+use serde::Deserialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Identity attributes of an applicant, as screened inside the enclave. Only
+/// attributes covered by the provider signature may be set: an unset attribute
+/// never rules a list entry out.
+pub struct Applicant<'a> {
+    pub full_name: &'a str,
+    pub date_of_birth: Option<&'a str>,
+    pub nationality: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct CsvRow {
+    name: String,
+    #[serde(default)]
+    aliases: String,
+    #[serde(default)]
+    date_of_birth: String,
+    #[serde(default)]
+    nationality: String,
+}
+
+#[derive(Deserialize)]
+struct ListEntry {
+    name: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    date_of_birth: Option<String>,
+    #[serde(default)]
+    nationality: Option<String>,
+}
+
+/// Attributes that can rule a name match out.
+struct EntryAttributes {
+    date_of_birth: Option<String>,
+    nationality: Option<String>,
+}
+
+impl EntryAttributes {
+    fn from_entry(entry: &ListEntry) -> Self {
+        EntryAttributes {
+            date_of_birth: entry
+                .date_of_birth
+                .as_ref()
+                .map(|dob| dob.trim().to_string()),
+            nationality: entry.nationality.as_ref().map(|n| {
+                normalize_country(n)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| n.trim().to_uppercase())
+            }),
+        }
+    }
+
+    /// Attributes missing on either side never rule an entry out.
+    fn compatible_with(&self, applicant: &Applicant) -> bool {
+        let dob_matches = match (&self.date_of_birth, applicant.date_of_birth) {
+            // Lists often only carry the birth year, so compare by prefix.
+            (Some(listed), Some(dob)) => dob.trim().starts_with(listed.as_str()),
+            _ => true,
+        };
+        let nationality_matches = match (&self.nationality, applicant.nationality) {
+            (Some(listed), Some(nationality)) => listed.eq_ignore_ascii_case(nationality.trim()),
+            _ => true,
+        };
+        dob_matches && nationality_matches
+    }
+}
+
+/// Parses CSV (`;`-separated aliases) or JSON list data into entries.
+fn parse_entries(format: ScreeningListFormat, data: &str) -> Result<Vec<ListEntry>, String> {
+    match format {
+        ScreeningListFormat::Json => serde_json::from_str(data).map_err(|e| e.to_string()),
+        ScreeningListFormat::Csv => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data.as_bytes())
+            .deserialize::<CsvRow>()
+            .map(|row| {
+                let row = row.map_err(|e| e.to_string())?;
+                Ok(ListEntry {
+                    name: row.name,
+                    aliases: row
+                        .aliases
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|alias| !alias.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    date_of_birth: Some(row.date_of_birth).filter(|v| !v.is_empty()),
+                    nationality: Some(row.nationality).filter(|v| !v.is_empty()),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Parsed sanctions dataset, indexed by normalized name and alias.
+pub struct SanctionsList {
+    pub version: String,
+    entries: Vec<EntryAttributes>,
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl SanctionsList {
+    pub fn parse(config: &SanctionsListConfig) -> Result<Self, String> {
+        let rows = parse_entries(config.format, &config.data)?;
+        let mut list = SanctionsList {
+            version: config.version.clone(),
+            entries: Vec::with_capacity(rows.len()),
+            by_name: HashMap::new(),
+        };
+        for row in rows {
+            let index = list.entries.len();
+            for name in std::iter::once(&row.name).chain(row.aliases.iter()) {
+                let key = normalize_name(name);
+                if !key.is_empty() {
+                    list.by_name.entry(key).or_default().push(index);
+                }
+            }
+            list.entries.push(EntryAttributes::from_entry(&row));
+        }
+        if list.by_name.is_empty() {
+            return Err("list contains no names".to_string());
+        }
+        Ok(list)
+    }
+
+    /// True when the applicant's name matches an entry (or one of its aliases) and
+    /// neither the date of birth nor the nationality rules that entry out.
+    pub fn is_listed(&self, applicant: &Applicant) -> bool {
+        let Some(candidates) = self.by_name.get(&normalize_name(applicant.full_name)) else {
+            return false;
+        };
+        candidates
+            .iter()
+            .any(|&index| self.entries[index].compatible_with(applicant))
+    }
+}
+
+/// Default Jaro-Winkler score from which a name counts as a PEP match.
+const DEFAULT_PEP_MATCH_THRESHOLD: f64 = 0.92;
+
+/// How close the best PEP match was. Scores between the threshold and 1.0 are
+/// split into three equal bands.
+#[derive(
+    Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema,
+)]
+#[repr(u8)]
+pub enum PepMatchConfidence {
+    None = 0,
+    Low = 1,
+    Medium = 2,
+    High = 3,
+}
+
+/// Parsed politically-exposed-persons dataset, matched fuzzily.
+pub struct PepList {
+    pub version: String,
+    pub match_threshold: f64,
+    names: Vec<(String, usize)>,
+    entries: Vec<EntryAttributes>,
+}
+
+impl PepList {
+    pub fn parse(config: &PepListConfig) -> Result<Self, String> {
+        let match_threshold = config
+            .match_threshold
+            .unwrap_or(DEFAULT_PEP_MATCH_THRESHOLD);
+        if !(match_threshold > 0.0 && match_threshold <= 1.0) {
+            return Err(format!(
+                "match_threshold must be in (0, 1], got {}",
+                match_threshold
+            ));
+        }
+        let rows = parse_entries(config.format, &config.data)?;
+        let mut list = PepList {
+            version: config.version.clone(),
+            match_threshold,
+            names: Vec::new(),
+            entries: Vec::with_capacity(rows.len()),
+        };
+        for row in rows {
+            let index = list.entries.len();
+            for name in std::iter::once(&row.name).chain(row.aliases.iter()) {
+                let key = normalize_name(name);
+                if !key.is_empty() {
+                    list.names.push((key, index));
+                }
+            }
+            list.entries.push(EntryAttributes::from_entry(&row));
+        }
+        if list.names.is_empty() {
+            return Err("list contains no names".to_string());
+        }
+        Ok(list)
+    }
+
+    /// Best Jaro-Winkler score between the applicant's normalized name and any
+    /// compatible entry, bucketed against the threshold.
+    pub fn screen(&self, applicant: &Applicant) -> PepMatchConfidence {
+        let name = normalize_name(applicant.full_name);
+        let best = self
+            .names
+            .iter()
+            .filter(|(_, index)| self.entries[*index].compatible_with(applicant))
+            .map(|(listed, _)| strsim::jaro_winkler(&name, listed))
+            .fold(0.0, f64::max);
+        if best < self.match_threshold {
+            return PepMatchConfidence::None;
+        }
+        let band = (1.0 - self.match_threshold) / 3.0;
+        if best >= self.match_threshold + 2.0 * band {
+            PepMatchConfidence::High
+        } else if best >= self.match_threshold + band {
+            PepMatchConfidence::Medium
+        } else {
+            PepMatchConfidence::Low
+        }
+    }
+}
+
+/// Transliterates to ASCII, lowercases, drops punctuation and sorts the name
+/// tokens, so "Doe, John" and "JOHN DOE" normalize to the same key.
+pub(crate) fn normalize_name(name: &str) -> String {
+    let ascii = deunicode::deunicode(name).to_lowercase();
+    let mut tokens: Vec<&str> = ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .collect();
+    tokens.sort_unstable();
+    tokens.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SANCTIONS_CSV: &str = "name,aliases,date_of_birth,nationality
+\"PETROV, Ivan Sergeyevich\",Ivan Petrov,1961,RU
+Jane Roe,,1980-05-01,GB
+";
+
+    fn sanctions() -> SanctionsList {
+        SanctionsList::parse(&SanctionsListConfig {
+            version: "test".to_string(),
+            format: ScreeningListFormat::Csv,
+            data: SANCTIONS_CSV.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn applicant<'a>(
+        full_name: &'a str,
+        date_of_birth: Option<&'a str>,
+        nationality: Option<&'a str>,
+    ) -> Applicant<'a> {
+        Applicant {
+            full_name,
+            date_of_birth,
+            nationality,
+        }
+    }
+
+    #[test]
+    fn sanctions_match_names_and_aliases() {
+        let list = sanctions();
+        assert!(list.is_listed(&applicant("Ivan Petrov", None, None)));
+        assert!(list.is_listed(&applicant("Petrov Ivan Sergeyevich", None, None)));
+        assert!(!list.is_listed(&applicant("Ivan Petrova", None, None)));
+    }
+
+    #[test]
+    fn sanctions_attributes_rule_entries_out() {
+        let list = sanctions();
+        assert!(list.is_listed(&applicant("Ivan Petrov", Some("1961-03-02"), Some("RU"))));
+        assert!(!list.is_listed(&applicant("Ivan Petrov", Some("1962-03-02"), None)));
+        assert!(!list.is_listed(&applicant("Ivan Petrov", None, Some("DE"))));
+        assert!(!list.is_listed(&applicant("Jane Roe", Some("1980-05-02"), None)));
+    }
+
+    #[test]
+    fn sanctions_unset_attributes_never_rule_entries_out() {
+        let list = sanctions();
+        assert!(list.is_listed(&applicant("Jane Roe", None, None)));
+        assert!(list.is_listed(&applicant("Jane Roe", Some("1980-05-01"), None)));
+    }
+
+    fn pep_list(match_threshold: Option<f64>) -> PepList {
+        PepList::parse(&PepListConfig {
+            version: "test".to_string(),
+            format: ScreeningListFormat::Json,
+            data: r#"[{"name": "Alexander Ivanov", "nationality": "RU", "date_of_birth": "1960"}]"#
+                .to_string(),
+            match_threshold,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn normalize_name_transliterates_and_sorts_tokens() {
+        assert_eq!(normalize_name("Doe, John"), "doe john");
+        assert_eq!(normalize_name("JOHN  DOE"), "doe john");
+        assert_eq!(normalize_name("José Müller"), "jose muller");
+        assert_eq!(normalize_name("Zoë Dupont-Laurent"), "dupont laurent zoe");
+        assert_eq!(normalize_name("Иван Петров"), "ivan petrov");
+        assert_eq!(normalize_name(" ,. "), "");
+    }
+
+    #[test]
+    fn pep_default_threshold_is_0_92() {
+        let list = pep_list(None);
+        assert_eq!(list.match_threshold, 0.92);
+        // Jaro-Winkler 0.9011: a match only under a lower threshold.
+        let applicant = applicant("Alex Ivanov", None, None);
+        assert_eq!(list.screen(&applicant), PepMatchConfidence::None);
+        assert_eq!(
+            pep_list(Some(0.9)).screen(&applicant),
+            PepMatchConfidence::Low
+        );
+    }
+
+    #[test]
+    fn pep_confidence_bands_split_the_range_above_the_threshold() {
+        let list = pep_list(None);
+        let screen = |name| list.screen(&applicant(name, None, None));
+        // 0.9417, 0.9483 and 0.9750 against bands starting at 0.92, 0.9467 and 0.9733.
+        assert_eq!(screen("Aleksandr Ivanov"), PepMatchConfidence::Low);
+        assert_eq!(screen("Alexandra Ivanov"), PepMatchConfidence::Medium);
+        assert_eq!(screen("Alexander Ivan"), PepMatchConfidence::High);
+        assert_eq!(screen("IVANOV, Alexander"), PepMatchConfidence::High);
+        assert_eq!(screen("Alexander Petrov"), PepMatchConfidence::None);
+    }
+
+    #[test]
+    fn pep_attributes_only_rule_out_when_set() {
+        let list = pep_list(None);
+        let screen =
+            |dob, nationality| list.screen(&applicant("Alexander Ivanov", dob, nationality));
+        assert_eq!(screen(None, None), PepMatchConfidence::High);
+        assert_eq!(
+            screen(Some("1960-01-01"), Some("RU")),
+            PepMatchConfidence::High
+        );
+        assert_eq!(screen(None, Some("DE")), PepMatchConfidence::None);
+        assert_eq!(screen(Some("1970-01-01"), None), PepMatchConfidence::None);
+    }
+
+    #[test]
+    fn pep_threshold_must_be_in_range() {
+        for threshold in [0.0, 1.5, f64::NAN] {
+            assert!(PepList::parse(&PepListConfig {
+                version: "test".to_string(),
+                format: ScreeningListFormat::Json,
+                data: r#"[{"name": "A B"}]"#.to_string(),
+                match_threshold: Some(threshold),
+            })
+            .is_err());
+        }
+    }
+}
@@ -0,0 +1,558 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::credentials::{
+    base64url, base64url_decode, enclave_did, multibase_key, sign_jws, verify_jws,
+};
+use super::KycCredential;
+use crate::common::EnclaveKey;
+use crate::EnclaveError;
+use fastcrypto::ed25519::Ed25519PublicKey;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+/// `vct` of the KYC credential type.
+pub const KYC_VCT: &str = "urn:kychook:kyc:1";
+
+/// Sui signature scheme flag of Ed25519 keys.
+const ED25519_SCHEME_FLAG: u8 = 0x00;
+
+const DISCLOSURE_SALT_LEN: usize = 16;
+
+/// Allowed clock skew for the `iat` of a key binding JWT.
+const KEY_BINDING_SKEW_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SdJwtCredential {
+    /// `<issuer-jwt>~<disclosure>~...~`, ready for the holder to append a key
+    /// binding JWT.
+    pub sd_jwt: String,
+    pub issuer: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SdJwtError {
+    #[error("malformed SD-JWT: {0}")]
+    Malformed(String),
+    #[error("invalid issuer signature")]
+    InvalidIssuerSignature,
+    #[error("credential expired at {0}")]
+    Expired(u64),
+    #[error("disclosure {0} is not covered by the issuer signature")]
+    UnknownDisclosure(String),
+    #[error("invalid key binding: {0}")]
+    InvalidKeyBinding(String),
+}
+
+/// What the verifier expects in the holder's key binding JWT.
+#[derive(Debug, Clone)]
+pub struct KeyBindingCheck<'a> {
+    pub audience: &'a str,
+    pub nonce: &'a str,
+    /// Oldest acceptable `iat`, relative to `now_secs`.
+    pub max_age_secs: u64,
+}
+
+/// Claims of a verified SD-JWT presentation.
+#[derive(Debug, Clone)]
+pub struct VerifiedSdJwt {
+    pub issuer: String,
+    /// Ed25519 key from `cnf.jwk`; hashes to the `userWallet` of the credential.
+    pub holder_public_key: Vec<u8>,
+    /// Only the claims the holder disclosed.
+    pub claims: Map<String, Value>,
+    pub key_bound: bool,
+}
+
+/// Sui address of an Ed25519 public key: Blake2b-256 of the scheme flag and key.
+pub fn sui_address_of_ed25519(public_key: &[u8]) -> String {
+    let mut bytes = vec![ED25519_SCHEME_FLAG];
+    bytes.extend_from_slice(public_key);
+    format!("0x{}", Hex::encode(Blake2b256::digest(bytes).digest))
+}
+
+fn malformed(what: &str) -> SdJwtError {
+    SdJwtError::Malformed(what.to_string())
+}
+
+fn disclosure_digest(disclosure: &str) -> String {
+    base64url(&Sha256::digest(disclosure.as_bytes()))
+}
+
+/// Re-issues a signed KYC result as an SD-JWT VC from the same enclave key. Every
+/// field of the payload becomes its own disclosure and `cnf` binds the credential
+/// to `holder_public_key`.
+pub(crate) fn to_sd_jwt(
+    key: &EnclaveKey,
+    credential: KycCredential,
+    holder_public_key: &[u8],
+) -> Result<SdJwtCredential, EnclaveError> {
+    let KycCredential::Plain(signed) = credential else {
+        return Err(EnclaveError::GenericError(
+            "commitFields cannot be combined with format=sd_jwt".to_string(),
+        ));
+    };
+    let payload = signed.response.data;
+    let Value::Object(fields) = serde_json::to_value(&payload).expect("should not fail") else {
+        unreachable!("KycResponsePayload serializes to an object");
+    };
+
+    let mut disclosures = Vec::with_capacity(fields.len());
+    let mut digests = Vec::with_capacity(fields.len());
+    for (name, value) in fields {
+        let mut salt = [0u8; DISCLOSURE_SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let disclosure = base64url(
+            json!([base64url(&salt), name, value])
+                .to_string()
+                .as_bytes(),
+        );
+        digests.push(disclosure_digest(&disclosure));
+        disclosures.push(disclosure);
+    }
+    // Sorted so the digest order does not reveal which claim is which.
+    digests.sort();
+
+    let public_key = key.keypair.public().as_bytes().to_vec();
+    let issuer = enclave_did(&public_key);
+    let header = json!({
+        "alg": "EdDSA",
+        "typ": "dc+sd-jwt",
+        "kid": format!("{}#{}", issuer, multibase_key(&public_key)),
+    });
+    let claims = json!({
+        "iss": issuer,
+        "vct": KYC_VCT,
+        "iat": payload.issuedAtMs / 1000,
+        "exp": payload.expiresAtMs / 1000,
+        "cnf": {
+            "jwk": { "kty": "OKP", "crv": "Ed25519", "x": base64url(holder_public_key) },
+        },
+        "_sd_alg": "sha-256",
+        "_sd": digests,
+    });
+
+    let mut sd_jwt = sign_jws(key, &header, &claims);
+    for disclosure in disclosures {
+        sd_jwt.push('~');
+        sd_jwt.push_str(&disclosure);
+    }
+    sd_jwt.push('~');
+    Ok(SdJwtCredential { sd_jwt, issuer })
+}
+
+/// Verifies an SD-JWT presentation issued by the enclave key `issuer_public_key`:
+/// the issuer signature and expiry, that every disclosure is covered by `_sd`, and,
+/// when `key_binding` is given, the holder's key binding JWT against `cnf`.
+pub fn verify_sd_jwt(
+    presentation: &str,
+    issuer_public_key: &Ed25519PublicKey,
+    key_binding: Option<&KeyBindingCheck>,
+    now_secs: u64,
+) -> Result<VerifiedSdJwt, SdJwtError> {
+    let (signed_part, key_binding_jwt) = presentation
+        .rsplit_once('~')
+        .ok_or_else(|| SdJwtError::Malformed("missing '~' separator".to_string()))?;
+    let mut parts = signed_part.split('~');
+    let issuer_jwt = parts.next().unwrap_or_default();
+
+    let (header, claims) = verify_jws(issuer_jwt, issuer_public_key, malformed, || {
+        SdJwtError::InvalidIssuerSignature
+    })?;
+    if header["typ"] != "dc+sd-jwt" || claims["_sd_alg"] != "sha-256" {
+        return Err(SdJwtError::Malformed(
+            "expected typ dc+sd-jwt and _sd_alg sha-256".to_string(),
+        ));
+    }
+    let issuer = enclave_did(issuer_public_key.as_bytes());
+    if claims["iss"] != issuer.as_str() {
+        return Err(SdJwtError::Malformed(format!("iss is not {}", issuer)));
+    }
+    let expires_at = claims["exp"]
+        .as_u64()
+        .ok_or_else(|| SdJwtError::Malformed("missing exp".to_string()))?;
+    if now_secs >= expires_at {
+        return Err(SdJwtError::Expired(expires_at));
+    }
+    let holder_public_key = claims["cnf"]["jwk"]["x"]
+        .as_str()
+        .and_then(base64url_decode)
+        .and_then(|bytes| Ed25519PublicKey::from_bytes(&bytes).ok())
+        .ok_or_else(|| SdJwtError::Malformed("cnf.jwk must be an Ed25519 key".to_string()))?;
+
+    let mut digests: HashSet<&str> = claims["_sd"]
+        .as_array()
+        .ok_or_else(|| SdJwtError::Malformed("missing _sd".to_string()))?
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    let mut disclosed = Map::new();
+    for disclosure in parts {
+        if !digests.remove(disclosure_digest(disclosure).as_str()) {
+            return Err(SdJwtError::UnknownDisclosure(disclosure.to_string()));
+        }
+        let decoded = base64url_decode(disclosure)
+            .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+        let Some([_salt, Value::String(name), value]) = decoded
+            .as_ref()
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+        else {
+            return Err(SdJwtError::Malformed(format!(
+                "disclosure {} must be [salt, name, value]",
+                disclosure
+            )));
+        };
+        if disclosed.insert(name.clone(), value.clone()).is_some() {
+            return Err(SdJwtError::Malformed(format!(
+                "{} is disclosed twice",
+                name
+            )));
+        }
+    }
+
+    let invalid = |reason: &str| SdJwtError::InvalidKeyBinding(reason.to_string());
+    let Some(check) = key_binding else {
+        // An unchecked key binding JWT would be reported as if it held.
+        if !key_binding_jwt.is_empty() {
+            return Err(invalid("key binding JWT was not requested"));
+        }
+        return Ok(VerifiedSdJwt {
+            issuer,
+            holder_public_key: holder_public_key.as_bytes().to_vec(),
+            claims: disclosed,
+            key_bound: false,
+        });
+    };
+    if key_binding_jwt.is_empty() {
+        return Err(invalid("presentation has no key binding JWT"));
+    }
+    let (header, binding) = verify_jws(key_binding_jwt, &holder_public_key, malformed, || {
+        invalid("signature does not match cnf")
+    })?;
+    let sd_hash = base64url(&Sha256::digest(format!("{}~", signed_part).as_bytes()));
+    let issued_at = binding["iat"]
+        .as_u64()
+        .ok_or_else(|| SdJwtError::Malformed("key binding JWT has no iat".to_string()))?;
+    if header["typ"] != "kb+jwt" {
+        return Err(invalid("typ must be kb+jwt"));
+    } else if binding["aud"] != check.audience || binding["nonce"] != check.nonce {
+        return Err(invalid("aud or nonce does not match"));
+    } else if binding["sd_hash"] != sd_hash.as_str() {
+        return Err(invalid("sd_hash does not match the presentation"));
+    } else if issued_at.saturating_add(check.max_age_secs) < now_secs
+        || issued_at > now_secs.saturating_add(KEY_BINDING_SKEW_SECS)
+    {
+        return Err(invalid("iat is outside the accepted window"));
+    }
+
+    Ok(VerifiedSdJwt {
+        issuer,
+        holder_public_key: holder_public_key.as_bytes().to_vec(),
+        claims: disclosed,
+        key_bound: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{to_signed_response, IntentScope};
+
+    const ISSUED_AT_SECS: u64 = 1_750_000_000;
+    const EXPIRES_AT_SECS: u64 = ISSUED_AT_SECS + 86_400;
+    const NOW_SECS: u64 = ISSUED_AT_SECS + 60;
+
+    fn holder() -> EnclaveKey {
+        EnclaveKey::generate(0)
+    }
+
+    fn issue(issuer: &EnclaveKey, holder: &EnclaveKey) -> String {
+        let payload = serde_json::from_value(json!({
+            "userWallet": sui_address_of_ed25519(holder.keypair.public().as_bytes()),
+            "providerId": "p1",
+            "kycLevel": 2,
+            "isPep": false,
+            "isSanctioned": false,
+            "blobId": "blob",
+            "docHash": "0xab",
+            "walrusCid": "blob",
+            "teeMeasurement": "0x00",
+            "nationality": "DE",
+            "cardArtCid": "cid",
+            "issuedAtMs": ISSUED_AT_SECS * 1000,
+            "expiresAtMs": EXPIRES_AT_SECS * 1000,
+            "sanctionsListVersion": null,
+            "pepMatchConfidence": 0,
+            "pepListVersion": null,
+            "predicates": [],
+        }))
+        .unwrap();
+        let signed = to_signed_response(&issuer.keypair, payload, 0, IntentScope::ProcessData);
+        let holder_key = holder.keypair.public().as_bytes();
+        to_sd_jwt(issuer, KycCredential::Plain(signed), holder_key)
+            .unwrap()
+            .sd_jwt
+    }
+
+    /// Splits `<issuer-jwt>~<disclosures>~` into the JWT and the disclosures.
+    fn split(sd_jwt: &str) -> (&str, Vec<&str>) {
+        let mut parts: Vec<&str> = sd_jwt.trim_end_matches('~').split('~').collect();
+        let issuer_jwt = parts.remove(0);
+        (issuer_jwt, parts)
+    }
+
+    fn disclosure_name(disclosure: &str) -> String {
+        let decoded: Value =
+            serde_json::from_slice(&base64url_decode(disclosure).unwrap()).unwrap();
+        decoded[1].as_str().unwrap().to_string()
+    }
+
+    /// Keeps only the disclosures of `names`.
+    fn present(sd_jwt: &str, names: &[&str]) -> String {
+        let (issuer_jwt, disclosures) = split(sd_jwt);
+        let mut presentation = issuer_jwt.to_string();
+        for disclosure in disclosures {
+            if names.contains(&disclosure_name(disclosure).as_str()) {
+                presentation.push('~');
+                presentation.push_str(disclosure);
+            }
+        }
+        presentation + "~"
+    }
+
+    fn key_binding(holder: &EnclaveKey, presentation: &str, claims: Value) -> String {
+        let mut binding = json!({
+            "iat": NOW_SECS,
+            "aud": "https://verifier.example",
+            "nonce": "n-0S6_WzA2Mj",
+            "sd_hash": base64url(&Sha256::digest(presentation.as_bytes())),
+        });
+        binding
+            .as_object_mut()
+            .unwrap()
+            .extend(claims.as_object().unwrap().clone());
+        let jwt = sign_jws(
+            holder,
+            &json!({ "alg": "EdDSA", "typ": "kb+jwt" }),
+            &binding,
+        );
+        format!("{}{}", presentation, jwt)
+    }
+
+    fn check() -> KeyBindingCheck<'static> {
+        KeyBindingCheck {
+            audience: "https://verifier.example",
+            nonce: "n-0S6_WzA2Mj",
+            max_age_secs: 300,
+        }
+    }
+
+    fn verify(
+        issuer: &EnclaveKey,
+        presentation: &str,
+        key_binding: Option<&KeyBindingCheck>,
+    ) -> Result<VerifiedSdJwt, SdJwtError> {
+        verify_sd_jwt(presentation, issuer.keypair.public(), key_binding, NOW_SECS)
+    }
+
+    #[test]
+    fn issued_credential_verifies_with_all_disclosures() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let verified = verify(&issuer, &issue(&issuer, &holder), None).unwrap();
+        assert_eq!(
+            verified.issuer,
+            enclave_did(issuer.keypair.public().as_bytes())
+        );
+        assert_eq!(
+            verified.holder_public_key,
+            holder.keypair.public().as_bytes()
+        );
+        assert_eq!(verified.claims.len(), 17);
+        assert_eq!(verified.claims["kycLevel"], 2);
+        assert!(!verified.key_bound);
+    }
+
+    #[test]
+    fn dropped_disclosures_are_withheld() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let presentation = present(&issue(&issuer, &holder), &["kycLevel", "isSanctioned"]);
+        let verified = verify(&issuer, &presentation, None).unwrap();
+        let names: Vec<&str> = verified.claims.keys().map(String::as_str).collect();
+        assert_eq!(names, ["isSanctioned", "kycLevel"]);
+    }
+
+    #[test]
+    fn forged_disclosures_are_rejected() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let presentation = present(&issue(&issuer, &holder), &["isSanctioned"]);
+        let forged = base64url(json!(["c2FsdA", "kycLevel", 3]).to_string().as_bytes());
+        assert!(matches!(
+            verify(&issuer, &format!("{}{}~", presentation, forged), None),
+            Err(SdJwtError::UnknownDisclosure(disclosure)) if disclosure == forged
+        ));
+    }
+
+    #[test]
+    fn tampered_issuer_jwt_is_rejected() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let sd_jwt = issue(&issuer, &holder);
+        assert!(matches!(
+            verify(&EnclaveKey::generate(0), &sd_jwt, None),
+            Err(SdJwtError::InvalidIssuerSignature)
+        ));
+
+        let (issuer_jwt, _) = split(&sd_jwt);
+        let mut segments: Vec<String> = issuer_jwt.split('.').map(str::to_string).collect();
+        let mut claims: Value =
+            serde_json::from_slice(&base64url_decode(&segments[1]).unwrap()).unwrap();
+        claims["exp"] = json!(EXPIRES_AT_SECS * 2);
+        segments[1] = base64url(claims.to_string().as_bytes());
+        assert!(matches!(
+            verify(&issuer, &format!("{}~", segments.join(".")), None),
+            Err(SdJwtError::InvalidIssuerSignature)
+        ));
+    }
+
+    #[test]
+    fn duplicated_claims_are_rejected() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let sd_jwt = issue(&issuer, &holder);
+        let (issuer_jwt, disclosures) = split(&sd_jwt);
+        let repeated = format!("{}~{}~{}~", issuer_jwt, disclosures[0], disclosures[0]);
+        assert!(matches!(
+            verify(&issuer, &repeated, None),
+            Err(SdJwtError::UnknownDisclosure(_))
+        ));
+
+        // Two distinct disclosures of one name, both covered by the issuer.
+        let first = base64url(json!(["c2FsdDE", "kycLevel", 1]).to_string().as_bytes());
+        let second = base64url(json!(["c2FsdDI", "kycLevel", 3]).to_string().as_bytes());
+        let public_key = issuer.keypair.public().as_bytes();
+        let claims = json!({
+            "iss": enclave_did(public_key),
+            "exp": EXPIRES_AT_SECS,
+            "cnf": { "jwk": { "kty": "OKP", "crv": "Ed25519",
+                "x": base64url(holder.keypair.public().as_bytes()) } },
+            "_sd_alg": "sha-256",
+            "_sd": [disclosure_digest(&first), disclosure_digest(&second)],
+        });
+        let jwt = sign_jws(
+            &issuer,
+            &json!({ "alg": "EdDSA", "typ": "dc+sd-jwt" }),
+            &claims,
+        );
+        assert!(matches!(
+            verify(&issuer, &format!("{}~{}~{}~", jwt, first, second), None),
+            Err(SdJwtError::Malformed(message)) if message == "kycLevel is disclosed twice"
+        ));
+    }
+
+    #[test]
+    fn expired_credentials_are_rejected() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let sd_jwt = issue(&issuer, &holder);
+        let public_key = issuer.keypair.public();
+        assert!(verify_sd_jwt(&sd_jwt, public_key, None, EXPIRES_AT_SECS - 1).is_ok());
+        assert!(matches!(
+            verify_sd_jwt(&sd_jwt, public_key, None, EXPIRES_AT_SECS),
+            Err(SdJwtError::Expired(EXPIRES_AT_SECS))
+        ));
+    }
+
+    #[test]
+    fn key_binding_verifies() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let presentation = present(&issue(&issuer, &holder), &["kycLevel"]);
+        let bound = key_binding(&holder, &presentation, json!({}));
+        let verified = verify(&issuer, &bound, Some(&check())).unwrap();
+        assert!(verified.key_bound);
+        assert_eq!(verified.claims.len(), 1);
+
+        assert!(matches!(
+            verify(&issuer, &presentation, Some(&check())),
+            Err(SdJwtError::InvalidKeyBinding(_))
+        ));
+    }
+
+    #[test]
+    fn unrequested_key_bindings_are_rejected() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let presentation = present(&issue(&issuer, &holder), &["kycLevel"]);
+        for trailing in [
+            key_binding(&holder, &presentation, json!({})),
+            presentation.clone() + "garbage",
+        ] {
+            assert!(matches!(
+                verify(&issuer, &trailing, None),
+                Err(SdJwtError::InvalidKeyBinding(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn bad_key_bindings_are_rejected() {
+        let (issuer, holder) = (EnclaveKey::generate(0), holder());
+        let sd_jwt = issue(&issuer, &holder);
+        let presentation = present(&sd_jwt, &["kycLevel"]);
+        let rejected = |bound: String| {
+            matches!(
+                verify(&issuer, &bound, Some(&check())),
+                Err(SdJwtError::InvalidKeyBinding(_))
+            )
+        };
+
+        assert!(rejected(key_binding(
+            &holder,
+            &presentation,
+            json!({ "aud": "https://other.example" })
+        )));
+        assert!(rejected(key_binding(
+            &holder,
+            &presentation,
+            json!({ "nonce": "replayed" })
+        )));
+        assert!(rejected(key_binding(
+            &holder,
+            &presentation,
+            json!({ "iat": NOW_SECS - 301 })
+        )));
+        assert!(rejected(key_binding(
+            &holder,
+            &presentation,
+            json!({ "iat": NOW_SECS + KEY_BINDING_SKEW_SECS + 1 })
+        )));
+        // Saturates rather than wrapping past the staleness check.
+        assert!(rejected(key_binding(
+            &holder,
+            &presentation,
+            json!({ "iat": u64::MAX })
+        )));
+        assert!(matches!(
+            verify(
+                &issuer,
+                &key_binding(&holder, &presentation, json!({ "iat": null })),
+                Some(&check())
+            ),
+            Err(SdJwtError::Malformed(_))
+        ));
+        assert!(rejected(key_binding(
+            &EnclaveKey::generate(0),
+            &presentation,
+            json!({})
+        )));
+
+        // A binding made for one set of disclosures cannot be moved to another.
+        let other = present(&sd_jwt, &["kycLevel", "nationality"]);
+        let bound = key_binding(&holder, &presentation, json!({}));
+        let (_, binding_jwt) = bound.rsplit_once('~').unwrap();
+        assert!(rejected(format!("{}{}", other, binding_jwt)));
+    }
+}
@@ -1,16 +1,32 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use super::blocklist::normalize_sui_address;
+use super::countries::normalize_country;
+use super::predicates::{load_predicates, PredicateConfig};
+use super::rate_limit::RateLimitConfig;
+use super::screening::{PepList, SanctionsList};
+use super::validation::{MAX_KYC_LEVEL, MIN_KYC_LEVEL};
+use crate::common::EnclaveMeasurement;
+use crate::FieldViolation;
 use fastcrypto::ed25519::Ed25519PublicKey;
 use fastcrypto::encoding::{Encoding, Hex};
 use fastcrypto::serde_helpers::ToFromByteArray;
 use fastcrypto::traits::ToFromBytes;
 use seal_sdk::types::{FetchKeyResponse, KeyId};
 use seal_sdk::{EncryptedObject, IBEPublicKey};
-use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::str::FromStr;
+use std::sync::Arc;
 use sui_sdk_types::ObjectId as ObjectID;
+use utoipa::ToSchema;
+
+/// Credential lifetime used when the config sets none: 365 days.
+const DEFAULT_CREDENTIAL_VALIDITY_MS: u64 = 365 * 24 * 60 * 60 * 1000;
+/// Length of `enclave_measurement`: PCR0, PCR1 and PCR2 of 48 bytes each.
+const ENCLAVE_MEASUREMENT_LEN: usize = 3 * 48;
 
 /// Generic hex -> Vec<KeyId> deserializer.
 fn deserialize_hex_vec<'de, D>(deserializer: D) -> Result<Vec<KeyId>, D::Error>
@@ -24,6 +40,24 @@ where
         .collect()
 }
 
+fn serialize_hex_vec<S>(ids: &[KeyId], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(ids.iter().map(Hex::encode))
+}
+
+/// Hex of the BCS encoding, the form `deserialize_encrypted_objects` and
+/// `deserialize_seal_responses` accept.
+fn serialize_bcs_hex<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    let bytes = bcs::to_bytes(value).map_err(serde::ser::Error::custom)?;
+    serializer.serialize_str(&Hex::encode(bytes))
+}
+
 fn deserialize_object_id<'de, D>(deserializer: D) -> Result<ObjectID, D::Error>
 where
     D: Deserializer<'de>,
@@ -134,46 +168,170 @@ impl TryFrom<SealConfigRaw> for SealConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct InitParameterLoadRequest {
+    #[schema(value_type = String)]
     pub enclave_object_id: ObjectID,
     pub initial_shared_version: u64,
-    #[serde(deserialize_with = "deserialize_hex_vec")]
+    /// Hex encoded Seal key ids.
+    #[serde(
+        serialize_with = "serialize_hex_vec",
+        deserialize_with = "deserialize_hex_vec"
+    )]
+    #[schema(value_type = Vec<String>)]
     pub ids: Vec<KeyId>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct InitParameterLoadResponse {
     pub encoded_request: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CompleteParameterLoadRequest {
-    #[serde(deserialize_with = "deserialize_encrypted_objects")]
+    /// Hex of the BCS encoded `Vec<EncryptedObject>`.
+    #[serde(
+        serialize_with = "serialize_bcs_hex",
+        deserialize_with = "deserialize_encrypted_objects"
+    )]
+    #[schema(value_type = String)]
     pub encrypted_objects: Vec<EncryptedObject>,
-    #[serde(deserialize_with = "deserialize_seal_responses")]
+    /// Hex of the BCS encoded `Vec<(ObjectID, FetchKeyResponse)>`.
+    #[serde(
+        serialize_with = "serialize_bcs_hex",
+        deserialize_with = "deserialize_seal_responses"
+    )]
+    #[schema(value_type = String)]
     pub seal_responses: Vec<(ObjectID, FetchKeyResponse)>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CompleteParameterLoadResponse {
     pub provider_count: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct KycProviderConfig {
     pub provider_id: String,
     pub public_key: String,
     #[serde(default)]
     pub allow_unsigned: bool,
+    /// Credential lifetime for this provider; overrides the level and default values.
+    #[serde(default)]
+    pub validity_ms: Option<u64>,
+    /// Endpoint that receives signed results of asynchronous jobs.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Requests this provider may submit; unlimited when unset.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct FileKycConfig {
+    /// Operator-assigned config revision, bound into attestations.
+    #[serde(default)]
+    pub version: u64,
     pub walrus_aggregator_url: String,
     pub walrus_timeout_ms: Option<u64>,
     pub enclave_measurement: String,
     pub providers: Vec<KycProviderConfig>,
+    /// Fallback credential lifetime; one year when unset.
+    #[serde(default)]
+    pub credential_validity_ms: Option<u64>,
+    /// Credential lifetime per `kycLevel`, used when the provider sets none.
+    #[serde(default)]
+    pub level_validity_ms: BTreeMap<u8, u64>,
+    /// Sanctions dataset applicants are screened against; screening is off when unset.
+    #[serde(default)]
+    pub sanctions_list: Option<SanctionsListConfig>,
+    /// PEP dataset applicants are fuzzily screened against; screening is off when unset.
+    #[serde(default)]
+    pub pep_list: Option<PepListConfig>,
+    /// Sui addresses that must never receive a KYC result.
+    #[serde(default)]
+    pub blocked_wallets: Vec<String>,
+    /// Nationalities refused issuance, as alpha-2, alpha-3 or country names.
+    #[serde(default)]
+    pub restricted_jurisdictions: Vec<String>,
+    /// Boolean claims requests can ask the enclave to derive and sign.
+    #[serde(default)]
+    pub predicates: Vec<PredicateConfig>,
+    /// Requests a single `userWallet` may trigger; unlimited when unset.
+    #[serde(default)]
+    pub wallet_rate_limit: Option<RateLimitConfig>,
+    /// Credential requests processed at once, across all providers.
+    #[serde(default)]
+    pub max_concurrent_requests: Option<u32>,
+}
+
+impl FileKycConfig {
+    /// Parses a config as JSON when it starts with `{`, otherwise as YAML.
+    pub fn parse(raw: &str) -> Result<Self, EnclaveConfigError> {
+        let parsed = if raw.trim_start().starts_with('{') {
+            serde_json::from_str(raw).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str(raw).map_err(|e| e.to_string())
+        };
+        parsed.map_err(EnclaveConfigError::InvalidFormat)
+    }
+}
+
+/// Checks that `url` is an absolute http(s) URL with a host.
+fn check_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("is not a valid URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err("must be an http(s) URL with a host".to_string());
+    }
+    Ok(())
+}
+
+fn check_rate_limit(field: &str, limit: &RateLimitConfig, violations: &mut Vec<FieldViolation>) {
+    if limit.requests_per_minute == 0 {
+        violations.push(FieldViolation::new(
+            &format!("{}.requests_per_minute", field),
+            "must be positive",
+        ));
+    }
+    if limit.burst == Some(0) {
+        violations.push(FieldViolation::new(
+            &format!("{}.burst", field),
+            "must be positive",
+        ));
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreeningListFormat {
+    Csv,
+    Json,
+}
+
+/// SDN-style sanctions dataset embedded in the config. CSV data has the header
+/// `name,aliases,date_of_birth,nationality` with `;`-separated aliases; JSON data is
+/// an array of objects with the same keys (`aliases` as an array).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SanctionsListConfig {
+    /// Publisher version of the list, e.g. the OFAC publication date.
+    pub version: String,
+    pub format: ScreeningListFormat,
+    pub data: String,
+}
+
+/// Politically-exposed-persons dataset, in the same formats as `SanctionsListConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PepListConfig {
+    pub version: String,
+    pub format: ScreeningListFormat,
+    pub data: String,
+    /// Jaro-Winkler score (0-1] from which a name counts as a match; 0.92 when unset.
+    #[serde(default)]
+    pub match_threshold: Option<f64>,
 }
 
 #[derive(Clone)]
@@ -182,14 +340,32 @@ pub struct ProviderRuntime {
     pub allow_unsigned: bool,
     pub raw_public_key: String,
     pub public_key: Ed25519PublicKey,
+    pub validity_ms: Option<u64>,
+    pub webhook_url: Option<String>,
+    pub rate_limit: Option<RateLimitConfig>,
 }
 
 #[derive(Clone)]
 pub struct KycRuntime {
+    pub version: u64,
+    /// SHA-256 over the canonical JSON encoding of the loaded `FileKycConfig`.
+    pub config_hash: [u8; 32],
     pub walrus_aggregator_url: String,
     pub walrus_timeout_ms: u64,
     pub enclave_measurement: String,
     pub providers: Vec<ProviderRuntime>,
+    pub credential_validity_ms: u64,
+    pub level_validity_ms: BTreeMap<u8, u64>,
+    pub sanctions: Option<Arc<SanctionsList>>,
+    pub pep: Option<Arc<PepList>>,
+    /// Normalized (`0x` + 64 hex) blocked addresses, from config or `/block_wallets`.
+    pub blocked_wallets: Arc<HashSet<String>>,
+    /// ISO 3166-1 alpha-2 codes of restricted nationalities.
+    pub restricted_jurisdictions: HashSet<&'static str>,
+    /// Configured predicates, with countries normalized to alpha-2.
+    pub predicates: Vec<PredicateConfig>,
+    pub wallet_rate_limit: Option<RateLimitConfig>,
+    pub max_concurrent_requests: Option<usize>,
 }
 
 impl KycRuntime {
@@ -198,53 +374,408 @@ impl KycRuntime {
             .iter()
             .find(|entry| entry.provider_id == provider_id)
     }
+
+    /// Lifetime of a credential issued by `provider` at `kyc_level`: the provider
+    /// override if set, else the per-level value, else the config default.
+    pub fn validity_ms(&self, provider: &ProviderRuntime, kyc_level: u8) -> u64 {
+        provider
+            .validity_ms
+            .or_else(|| self.level_validity_ms.get(&kyc_level).copied())
+            .unwrap_or(self.credential_validity_ms)
+    }
+
+    /// Replaces the declared `enclave_measurement` with the PCRs the enclave is
+    /// actually running, refusing configs that were written for another image.
+    pub fn bind_measurement(
+        &mut self,
+        measured: &EnclaveMeasurement,
+    ) -> Result<(), EnclaveConfigError> {
+        let measured = measured.to_hex();
+        let declared = self.enclave_measurement.trim().to_lowercase();
+        if declared.trim_start_matches("0x") != measured.trim_start_matches("0x") {
+            return Err(EnclaveConfigError::MeasurementMismatch {
+                declared: self.enclave_measurement.clone(),
+                measured,
+            });
+        }
+        self.enclave_measurement = measured;
+        Ok(())
+    }
+
+    /// Public summary of the active policy, committed to in attestation `user_data`.
+    pub fn commitment(&self) -> RuntimeCommitment {
+        RuntimeCommitment {
+            config_hash: Hex::encode(self.config_hash),
+            version: self.version,
+            providers: self
+                .providers
+                .iter()
+                .map(|provider| ProviderCommitment {
+                    provider_id: provider.provider_id.clone(),
+                    public_key: Hex::encode(provider.public_key.as_bytes()),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProviderCommitment {
+    pub provider_id: String,
+    pub public_key: String,
+}
+
+/// Config hash, version and provider set of the loaded `KycRuntime`. The SHA-256
+/// of its BCS encoding is what the enclave places in attestation `user_data`, so a
+/// verifier holding this struct can recompute and compare it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuntimeCommitment {
+    pub config_hash: String,
+    pub version: u64,
+    pub providers: Vec<ProviderCommitment>,
+}
+
+impl RuntimeCommitment {
+    pub fn digest(&self) -> [u8; 32] {
+        let bytes = bcs::to_bytes(self).expect("should not fail");
+        Sha256::digest(bytes).into()
+    }
 }
 
 impl TryFrom<FileKycConfig> for KycRuntime {
     type Error = EnclaveConfigError;
 
+    /// Validates the whole config and reports every problem at once, each with
+    /// its path in the config.
     fn try_from(cfg: FileKycConfig) -> Result<Self, Self::Error> {
+        let canonical = serde_json::to_vec(&cfg)
+            .map_err(|e| EnclaveConfigError::InvalidFormat(e.to_string()))?;
+        let config_hash: [u8; 32] = Sha256::digest(canonical).into();
+        let mut violations = Vec::new();
+        let positive = |field: &str, value: Option<u64>, violations: &mut Vec<FieldViolation>| {
+            if value == Some(0) {
+                violations.push(FieldViolation::new(field, "must be positive"));
+            }
+        };
+
+        if let Err(message) = check_url(&cfg.walrus_aggregator_url) {
+            violations.push(FieldViolation::new("walrus_aggregator_url", message));
+        }
+        positive("walrus_timeout_ms", cfg.walrus_timeout_ms, &mut violations);
+        match Hex::decode(cfg.enclave_measurement.trim().trim_start_matches("0x")) {
+            Ok(bytes) if bytes.len() == ENCLAVE_MEASUREMENT_LEN => {}
+            Ok(bytes) => violations.push(FieldViolation::new(
+                "enclave_measurement",
+                format!(
+                    "must be {} bytes (PCR0, PCR1 and PCR2), got {}",
+                    ENCLAVE_MEASUREMENT_LEN,
+                    bytes.len()
+                ),
+            )),
+            Err(_) => violations.push(FieldViolation::new(
+                "enclave_measurement",
+                "must be hex encoded",
+            )),
+        }
+        positive(
+            "credential_validity_ms",
+            cfg.credential_validity_ms,
+            &mut violations,
+        );
+        for (level, validity_ms) in &cfg.level_validity_ms {
+            let field = format!("level_validity_ms.{}", level);
+            if !(MIN_KYC_LEVEL..=MAX_KYC_LEVEL).contains(level) {
+                violations.push(FieldViolation::new(
+                    &field,
+                    format!(
+                        "is not a kycLevel between {} and {}",
+                        MIN_KYC_LEVEL, MAX_KYC_LEVEL
+                    ),
+                ));
+            }
+            positive(&field, Some(*validity_ms), &mut violations);
+        }
+
         if cfg.providers.is_empty() {
-            return Err(EnclaveConfigError::NoProviders);
+            violations.push(FieldViolation::new(
+                "providers",
+                "at least one provider is required",
+            ));
         }
         let mut providers = Vec::with_capacity(cfg.providers.len());
-        for provider in cfg.providers.iter() {
-            let key_bytes =
-                Hex::decode(provider.public_key.trim_start_matches("0x")).map_err(|e| {
-                    EnclaveConfigError::InvalidProviderKey {
-                        provider: provider.provider_id.clone(),
-                        error: e.to_string(),
-                    }
-                })?;
-            let public_key = Ed25519PublicKey::from_bytes(&key_bytes).map_err(|e| {
-                EnclaveConfigError::InvalidProviderKey {
-                    provider: provider.provider_id.clone(),
-                    error: e.to_string(),
+        let mut first_index = HashMap::new();
+        for (index, provider) in cfg.providers.iter().enumerate() {
+            let field = |name: &str| format!("providers[{}].{}", index, name);
+            if provider.provider_id.trim().is_empty() {
+                violations.push(FieldViolation::new(
+                    &field("provider_id"),
+                    "must not be empty",
+                ));
+            } else if let Some(first) = first_index.get(provider.provider_id.as_str()) {
+                violations.push(FieldViolation::new(
+                    &field("provider_id"),
+                    format!("duplicates providers[{}]", first),
+                ));
+            } else {
+                first_index.insert(provider.provider_id.as_str(), index);
+            }
+            positive(&field("validity_ms"), provider.validity_ms, &mut violations);
+            if let Some(Err(message)) = provider.webhook_url.as_deref().map(check_url) {
+                violations.push(FieldViolation::new(&field("webhook_url"), message));
+            }
+            if let Some(limit) = &provider.rate_limit {
+                check_rate_limit(&field("rate_limit"), limit, &mut violations);
+            }
+            let public_key = Hex::decode(provider.public_key.trim_start_matches("0x"))
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| Ed25519PublicKey::from_bytes(&bytes).map_err(|e| e.to_string()));
+            match public_key {
+                Ok(public_key) => providers.push(ProviderRuntime {
+                    provider_id: provider.provider_id.clone(),
+                    allow_unsigned: provider.allow_unsigned,
+                    raw_public_key: provider.public_key.clone(),
+                    public_key,
+                    validity_ms: provider.validity_ms,
+                    webhook_url: provider.webhook_url.clone(),
+                    rate_limit: provider.rate_limit,
+                }),
+                Err(e) => violations.push(FieldViolation::new(
+                    &field("public_key"),
+                    format!("is not a hex Ed25519 public key: {}", e),
+                )),
+            }
+        }
+
+        let sanctions = match cfg.sanctions_list.as_ref().map(SanctionsList::parse) {
+            Some(Ok(list)) => Some(Arc::new(list)),
+            Some(Err(e)) => {
+                violations.push(FieldViolation::new("sanctions_list", e));
+                None
+            }
+            None => None,
+        };
+        let pep = match cfg.pep_list.as_ref().map(PepList::parse) {
+            Some(Ok(list)) => Some(Arc::new(list)),
+            Some(Err(e)) => {
+                violations.push(FieldViolation::new("pep_list", e));
+                None
+            }
+            None => None,
+        };
+        let mut blocked_wallets = HashSet::new();
+        for (index, address) in cfg.blocked_wallets.iter().enumerate() {
+            match normalize_sui_address(address) {
+                Some(address) => {
+                    blocked_wallets.insert(address);
                 }
-            })?;
-            providers.push(ProviderRuntime {
-                provider_id: provider.provider_id.clone(),
-                allow_unsigned: provider.allow_unsigned,
-                raw_public_key: provider.public_key.clone(),
-                public_key,
-            });
+                None => violations.push(FieldViolation::new(
+                    &format!("blocked_wallets[{}]", index),
+                    format!("{} is not a Sui address", address),
+                )),
+            }
+        }
+        let mut restricted_jurisdictions = HashSet::new();
+        for (index, country) in cfg.restricted_jurisdictions.iter().enumerate() {
+            match normalize_country(country) {
+                Some(code) => {
+                    restricted_jurisdictions.insert(code);
+                }
+                None => violations.push(FieldViolation::new(
+                    &format!("restricted_jurisdictions[{}]", index),
+                    format!("unrecognized country {}", country),
+                )),
+            }
+        }
+        let predicates = load_predicates(&cfg.predicates, &mut violations);
+        if let Some(limit) = &cfg.wallet_rate_limit {
+            check_rate_limit("wallet_rate_limit", limit, &mut violations);
+        }
+        if cfg.max_concurrent_requests == Some(0) {
+            violations.push(FieldViolation::new(
+                "max_concurrent_requests",
+                "must be positive",
+            ));
         }
 
+        if !violations.is_empty() {
+            return Err(EnclaveConfigError::Invalid(violations));
+        }
         Ok(KycRuntime {
+            version: cfg.version,
+            config_hash,
             walrus_aggregator_url: cfg.walrus_aggregator_url,
             walrus_timeout_ms: cfg.walrus_timeout_ms.unwrap_or(8_000),
             enclave_measurement: cfg.enclave_measurement,
             providers,
+            credential_validity_ms: cfg
+                .credential_validity_ms
+                .unwrap_or(DEFAULT_CREDENTIAL_VALIDITY_MS),
+            level_validity_ms: cfg.level_validity_ms,
+            sanctions,
+            pep,
+            blocked_wallets: Arc::new(blocked_wallets),
+            restricted_jurisdictions,
+            predicates,
+            wallet_rate_limit: cfg.wallet_rate_limit,
+            max_concurrent_requests: cfg.max_concurrent_requests.map(|max| max as usize),
         })
     }
 }
 
+fn describe(violations: &[FieldViolation]) -> String {
+    violations
+        .iter()
+        .map(|violation| format!("{}: {}", violation.field, violation.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum EnclaveConfigError {
-    #[error("no providers configured")]
-    NoProviders,
-    #[error("invalid provider key for {provider}: {error}")]
-    InvalidProviderKey { provider: String, error: String },
     #[error("invalid JSON/YAML config: {0}")]
     InvalidFormat(String),
+    /// Every semantic problem found in the config, keyed by config path.
+    #[error("invalid KYC config: {}", describe(.0))]
+    Invalid(Vec<FieldViolation>),
+    #[error("config declares enclave_measurement {declared} but the enclave measures {measured}")]
+    MeasurementMismatch { declared: String, measured: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad";
+
+    fn config(extra: &str) -> String {
+        format!(
+            "walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             providers:\n\
+             - provider_id: p1\n  public_key: \"{}\"\n\
+             {}",
+            "00".repeat(ENCLAVE_MEASUREMENT_LEN),
+            PUBLIC_KEY,
+            extra
+        )
+    }
+
+    fn load(raw: &str) -> Result<KycRuntime, EnclaveConfigError> {
+        KycRuntime::try_from(FileKycConfig::parse(raw)?)
+    }
+
+    fn violations(raw: &str) -> Vec<(String, String)> {
+        match load(raw) {
+            Err(EnclaveConfigError::Invalid(violations)) => violations
+                .into_iter()
+                .map(|v| (v.field, v.message))
+                .collect(),
+            other => panic!("expected violations, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    fn fields(raw: &str) -> Vec<String> {
+        violations(raw)
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect()
+    }
+
+    #[test]
+    fn valid_config_loads_from_yaml_and_json() {
+        let runtime = load(&config("")).unwrap();
+        assert_eq!(runtime.providers.len(), 1);
+        assert_eq!(runtime.walrus_timeout_ms, 8_000);
+
+        let json = serde_json::to_string(&FileKycConfig::parse(&config("")).unwrap()).unwrap();
+        assert_eq!(load(&json).unwrap().config_hash, runtime.config_hash);
+    }
+
+    #[test]
+    fn duplicate_provider_id_is_reported() {
+        let raw = config(&format!(
+            "- provider_id: p1\n  public_key: \"{}\"\n",
+            PUBLIC_KEY
+        ));
+        assert_eq!(
+            violations(&raw),
+            [(
+                "providers[1].provider_id".to_string(),
+                "duplicates providers[0]".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn bad_urls_are_reported() {
+        let raw = config("  webhook_url: ftp://hooks.example\n")
+            .replace("https://aggregator.example", "not a url");
+        assert_eq!(
+            fields(&raw),
+            ["walrus_aggregator_url", "providers[0].webhook_url"]
+        );
+    }
+
+    #[test]
+    fn bad_measurements_are_reported() {
+        let short = config("").replace(&"00".repeat(ENCLAVE_MEASUREMENT_LEN), "0011");
+        assert_eq!(
+            violations(&short),
+            [(
+                "enclave_measurement".to_string(),
+                "must be 144 bytes (PCR0, PCR1 and PCR2), got 2".to_string()
+            )]
+        );
+        let not_hex = config("").replace(&"00".repeat(ENCLAVE_MEASUREMENT_LEN), "zz");
+        assert_eq!(
+            violations(&not_hex),
+            [(
+                "enclave_measurement".to_string(),
+                "must be hex encoded".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn every_error_is_reported_at_once() {
+        let raw = config(
+            "  validity_ms: 0\n\
+             - provider_id: p1\n  public_key: \"0x1234\"\n\
+             blocked_wallets: [nope]\n\
+             restricted_jurisdictions: [Atlantis]\n\
+             max_concurrent_requests: 0\n",
+        )
+        .replace("https://aggregator.example", "not a url");
+        assert_eq!(
+            fields(&raw),
+            [
+                "walrus_aggregator_url",
+                "providers[0].validity_ms",
+                "providers[1].provider_id",
+                "providers[1].public_key",
+                "blocked_wallets[0]",
+                "restricted_jurisdictions[0]",
+                "max_concurrent_requests",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        for raw in [
+            config("walrus_timeout: 5\n"),
+            config("  allow_unsinged: true\n"),
+            config("predicates:\n- name: adult\n  kind: age_at_least\n  years: 18\n  yaers: 21\n"),
+        ] {
+            assert!(
+                matches!(load(&raw), Err(EnclaveConfigError::InvalidFormat(_))),
+                "{}",
+                raw
+            );
+        }
+        load(&config(
+            "predicates:\n- name: adult\n  kind: age_at_least\n  years: 18\n",
+        ))
+        .unwrap();
+    }
 }
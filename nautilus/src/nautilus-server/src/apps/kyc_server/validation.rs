@@ -0,0 +1,415 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{
+    normalize_country, normalize_sui_address, sui_address_of_ed25519, CredentialFormat,
+    KycRequestPayload, COMMITTABLE_FIELDS,
+};
+use crate::{EnclaveError, FieldViolation};
+use axum::extract::rejection::JsonRejection;
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+
+pub const MIN_KYC_LEVEL: u8 = 1;
+pub const MAX_KYC_LEVEL: u8 = 3;
+const MAX_PROVIDER_ID_LEN: usize = 64;
+const MAX_BLOB_ID_LEN: usize = 128;
+const MAX_CARD_ART_CID_LEN: usize = 512;
+const MAX_FULL_NAME_LEN: usize = 256;
+const ED25519_SIGNATURE_LEN: usize = 64;
+/// Violation message for country fields `normalize_country` does not recognize.
+pub(crate) const INVALID_COUNTRY: &str =
+    "must be an ISO 3166-1 alpha-2 or alpha-3 code or a country name";
+
+/// Unwraps a JSON body, reporting malformed JSON, wrong types and unknown fields
+/// in the same structured form as field validation errors.
+pub(crate) fn parse_body<T>(body: Result<Json<T>, JsonRejection>) -> Result<T, EnclaveError> {
+    body.map(|Json(value)| value).map_err(|rejection| {
+        EnclaveError::InvalidRequest(vec![FieldViolation::new("body", rejection.body_text())])
+    })
+}
+
+/// Checks every field of a KYC payload without any I/O and reports all
+/// violations at once.
+pub(crate) fn validate_payload(payload: &KycRequestPayload) -> Result<(), EnclaveError> {
+    let mut violations = Vec::new();
+    let mut violation = |field: &str, message: String| {
+        violations.push(FieldViolation::new(field, message));
+    };
+
+    if normalize_sui_address(&payload.userWallet).is_none() {
+        violation(
+            "userWallet",
+            "must be a 0x-prefixed Sui address of at most 64 hex digits".to_string(),
+        );
+    }
+    check_text(
+        &mut violation,
+        "providerId",
+        &payload.providerId,
+        MAX_PROVIDER_ID_LEN,
+    );
+    if !(MIN_KYC_LEVEL..=MAX_KYC_LEVEL).contains(&payload.kycLevel) {
+        violation(
+            "kycLevel",
+            format!("must be between {} and {}", MIN_KYC_LEVEL, MAX_KYC_LEVEL),
+        );
+    }
+    check_text(&mut violation, "blobId", &payload.blobId, MAX_BLOB_ID_LEN);
+    // The blob id becomes part of the aggregator URL path.
+    if !payload
+        .blobId
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        violation(
+            "blobId",
+            "may only contain letters, digits, '-' and '_'".to_string(),
+        );
+    }
+    if hex_len(&payload.docHash) != Some(32) {
+        violation(
+            "docHash",
+            "must be a 32-byte SHA-256 digest in hex".to_string(),
+        );
+    }
+    if let Some(object) = &payload.walrusBlobObject {
+        if normalize_sui_address(object).is_none() {
+            violation(
+                "walrusBlobObject",
+                "must be a 0x-prefixed Sui object id".to_string(),
+            );
+        }
+    }
+    // `nationality` is checked by `check_policy`, after a blocked wallet has
+    // received its signed denial.
+    if payload.cardArtCid.len() > MAX_CARD_ART_CID_LEN {
+        violation(
+            "cardArtCid",
+            format!("must be at most {} bytes", MAX_CARD_ART_CID_LEN),
+        );
+    }
+    if let Some(signature) = &payload.providerSignature {
+        if hex_len(signature) != Some(ED25519_SIGNATURE_LEN) {
+            violation(
+                "providerSignature",
+                "must be a 64-byte Ed25519 signature in hex".to_string(),
+            );
+        }
+    }
+    if let Some(full_name) = &payload.fullName {
+        check_text(&mut violation, "fullName", full_name, MAX_FULL_NAME_LEN);
+    }
+    if let Some(date_of_birth) = &payload.dateOfBirth {
+        if !is_iso_date(date_of_birth) {
+            violation("dateOfBirth", "must be a YYYY-MM-DD date".to_string());
+        }
+    }
+
+    if let Some(residence) = &payload.residenceCountry {
+        if normalize_country(residence).is_none() {
+            violation("residenceCountry", INVALID_COUNTRY.to_string());
+        }
+    }
+    if let Some(predicates) = &payload.requestedPredicates {
+        for (index, predicate) in predicates.iter().enumerate() {
+            if predicates[..index].contains(predicate) {
+                violation(
+                    "requestedPredicates",
+                    format!("{} is listed twice", predicate),
+                );
+            }
+        }
+    }
+    if let Some(holder_key) = &payload.holderPublicKey {
+        match decode_hex(holder_key) {
+            Some(key) if key.len() == 32 => {
+                if normalize_sui_address(&payload.userWallet)
+                    .is_some_and(|wallet| wallet != sui_address_of_ed25519(&key))
+                {
+                    violation(
+                        "holderPublicKey",
+                        "is not the Ed25519 key of userWallet".to_string(),
+                    );
+                }
+            }
+            _ => violation(
+                "holderPublicKey",
+                "must be a 32-byte Ed25519 public key in hex".to_string(),
+            ),
+        }
+    }
+    if let Some(fields) = &payload.commitFields {
+        for (index, field) in fields.iter().enumerate() {
+            if !COMMITTABLE_FIELDS.contains(&field.as_str()) {
+                violation(
+                    "commitFields",
+                    format!("{} is not one of {}", field, COMMITTABLE_FIELDS.join(", ")),
+                );
+            } else if fields[..index].contains(field) {
+                violation("commitFields", format!("{} is listed twice", field));
+            }
+        }
+        // A non-zero confidence is only ever signed alongside isPep = true.
+        let commits = |name: &str| fields.iter().any(|field| field == name);
+        if commits("isPep") && !commits("pepMatchConfidence") {
+            violation(
+                "commitFields",
+                "must include pepMatchConfidence when committing isPep, which it reveals"
+                    .to_string(),
+            );
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(EnclaveError::InvalidRequest(violations))
+    }
+}
+
+/// Rejects `commitFields` for `/process_data_async`: job results are readable by
+/// anyone holding the job id and are sent to the provider's webhook, so they
+/// cannot carry the openings meant for the user.
+pub(crate) fn validate_async(payload: &KycRequestPayload) -> Result<(), EnclaveError> {
+    if payload
+        .commitFields
+        .as_ref()
+        .is_some_and(|fields| !fields.is_empty())
+    {
+        return Err(EnclaveError::InvalidRequest(vec![FieldViolation::new(
+            "commitFields",
+            "is not supported by /process_data_async; use /process_data to receive the openings",
+        )]));
+    }
+    Ok(())
+}
+
+/// Checks the request fields a `/process_data` output format depends on.
+pub(crate) fn validate_format(
+    format: CredentialFormat,
+    payload: &KycRequestPayload,
+) -> Result<(), EnclaveError> {
+    let mut violations = Vec::new();
+    if format != CredentialFormat::Nautilus
+        && payload
+            .commitFields
+            .as_ref()
+            .is_some_and(|fields| !fields.is_empty())
+    {
+        violations.push(FieldViolation::new(
+            "commitFields",
+            format!("cannot be combined with format={}", format.as_str()),
+        ));
+    }
+    if format == CredentialFormat::SdJwt && payload.holderPublicKey.is_none() {
+        violations.push(FieldViolation::new(
+            "holderPublicKey",
+            "is required for format=sd_jwt",
+        ));
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(EnclaveError::InvalidRequest(violations))
+    }
+}
+
+fn check_text(violation: &mut impl FnMut(&str, String), field: &str, value: &str, max: usize) {
+    if value.trim().is_empty() {
+        violation(field, "must not be empty".to_string());
+    } else if value.len() > max {
+        violation(field, format!("must be at most {} bytes", max));
+    }
+}
+
+/// Decodes an optionally 0x-prefixed hex string.
+pub(crate) fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    let hex = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    Hex::decode(hex).ok()
+}
+
+/// Decoded length of an optionally 0x-prefixed hex string.
+fn hex_len(value: &str) -> Option<usize> {
+    decode_hex(value).map(|bytes| bytes.len())
+}
+
+fn is_iso_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return false;
+    };
+    let number = |s: &str, len: usize| {
+        (s.len() == len && s.chars().all(|c| c.is_ascii_digit()))
+            .then(|| s.parse::<u32>().ok())
+            .flatten()
+    };
+    match (number(year, 4), number(month, 2), number(day, 2)) {
+        (Some(year), Some(month @ 1..=12), Some(day)) => {
+            (1..=days_in_month(year, month)).contains(&day)
+        }
+        _ => false,
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn payload(extra: Value) -> KycRequestPayload {
+        let mut value = json!({
+            "userWallet": "0x1",
+            "providerId": "p1",
+            "kycLevel": 2,
+            "blobId": "blob",
+            "docHash": format!("0x{}", "ab".repeat(32)),
+            "walrusBlobObject": null,
+            "nationality": "DE",
+            "cardArtCid": "cid",
+            "providerSignature": null,
+        });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// `(field, message)` of every violation, in report order.
+    fn violations(result: Result<(), EnclaveError>) -> Vec<(String, String)> {
+        match result {
+            Ok(()) => Vec::new(),
+            Err(EnclaveError::InvalidRequest(violations)) => violations
+                .into_iter()
+                .map(|v| (v.field, v.message))
+                .collect(),
+            Err(e) => panic!("expected InvalidRequest, got {}", e),
+        }
+    }
+
+    fn fields(extra: Value) -> Vec<String> {
+        violations(validate_payload(&payload(extra)))
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect()
+    }
+
+    #[test]
+    fn valid_payload_passes() {
+        assert!(fields(json!({})).is_empty());
+        assert!(fields(json!({
+            "fullName": "Ada Lovelace",
+            "dateOfBirth": "1815-12-10",
+            "residenceCountry": "GBR",
+            "providerSignature": "00".repeat(64),
+        }))
+        .is_empty());
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let reported = fields(json!({
+            "userWallet": "alice",
+            "providerId": " ",
+            "kycLevel": 4,
+            "blobId": "../blob",
+            "docHash": "0xab",
+            "providerSignature": "0x00",
+            "dateOfBirth": "1990-13-01",
+        }));
+        assert_eq!(
+            reported,
+            [
+                "userWallet",
+                "providerId",
+                "kycLevel",
+                "blobId",
+                "docHash",
+                "providerSignature",
+                "dateOfBirth"
+            ]
+        );
+    }
+
+    #[test]
+    fn impossible_dates_are_rejected() {
+        for date in [
+            "2023-02-29",
+            "2023-02-31",
+            "2023-04-31",
+            "1900-02-29",
+            "2024-00-10",
+        ] {
+            assert_eq!(
+                fields(json!({ "dateOfBirth": date })),
+                ["dateOfBirth"],
+                "{}",
+                date
+            );
+        }
+        for date in ["2024-02-29", "2000-02-29", "2023-01-31", "2023-04-30"] {
+            assert!(
+                fields(json!({ "dateOfBirth": date })).is_empty(),
+                "{}",
+                date
+            );
+        }
+        for date in ["1990-7-15", "15/07/1990", "1990-07-15T00:00", "+990-07-15"] {
+            assert!(!is_iso_date(date), "{}", date);
+        }
+    }
+
+    #[test]
+    fn malformed_bodies_become_field_violations() {
+        let missing = Json::<KycRequestPayload>::from_bytes(br#"{"userWallet": "0x1"}"#);
+        let reported = violations(parse_body(missing).map(|_| ()));
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].0, "body");
+        assert!(reported[0].1.contains("missing field"), "{}", reported[0].1);
+
+        let mut unknown = serde_json::to_value(payload(json!({}))).unwrap();
+        unknown["isAdmin"] = json!(true);
+        let unknown = Json::<KycRequestPayload>::from_bytes(unknown.to_string().as_bytes());
+        let reported = violations(parse_body(unknown).map(|_| ()));
+        assert!(
+            reported[0].1.contains("unknown field `isAdmin`"),
+            "{}",
+            reported[0].1
+        );
+    }
+
+    #[test]
+    fn formats_check_the_fields_they_need() {
+        let plain = payload(json!({}));
+        assert!(validate_format(CredentialFormat::Nautilus, &plain).is_ok());
+        assert!(validate_format(CredentialFormat::JwtVc, &plain).is_ok());
+        assert_eq!(
+            violations(validate_format(CredentialFormat::SdJwt, &plain)),
+            [(
+                "holderPublicKey".to_string(),
+                "is required for format=sd_jwt".to_string()
+            )]
+        );
+
+        let committed = payload(json!({ "commitFields": ["nationality"] }));
+        assert!(validate_format(CredentialFormat::Nautilus, &committed).is_ok());
+        let reported = violations(validate_format(CredentialFormat::SdJwt, &committed));
+        let reported: Vec<&str> = reported.iter().map(|(field, _)| field.as_str()).collect();
+        assert_eq!(reported, ["commitFields", "holderPublicKey"]);
+    }
+}
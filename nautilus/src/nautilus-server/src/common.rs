@@ -1,33 +1,38 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::app::RuntimeCommitment;
 use crate::AppState;
-use crate::EnclaveError;
-use axum::{extract::State, Json};
-use fastcrypto::traits::Signer;
+use crate::{EnclaveError, ErrorResponse};
+use axum::extract::{Query, State};
+use axum::Json;
+use fastcrypto::traits::{Signer, VerifyingKey};
 use fastcrypto::{encoding::Encoding, traits::ToFromBytes};
 use fastcrypto::{encoding::Hex, traits::KeyPair as FcKeyPair};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_repr::Deserialize_repr;
 use serde_repr::Serialize_repr;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::info;
+use utoipa::{IntoParams, ToSchema};
 
-use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::encoding::Base64;
 #[cfg(target_os = "linux")]
 use nsm_api::api::{Request as NsmRequest, Response as NsmResponse};
 #[cfg(target_os = "linux")]
 use nsm_api::driver;
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", feature = "dev-attestor"))]
 use serde_bytes::ByteBuf;
 /// ==== COMMON TYPES ====
 /// Intent message wrapper struct containing the intent scope and timestamp.
 /// This standardizes the serialized payload for signing.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct IntentMessage<T: Serialize> {
     pub intent: IntentScope,
     pub timestamp_ms: u64,
@@ -36,10 +41,14 @@ pub struct IntentMessage<T: Serialize> {
 
 /// Intent scope enum. Add new scope here if needed, each corresponds to a
 /// scope for signing. Replace in with your own intent per message type being signed by the enclave.
-#[derive(Serialize_repr, Deserialize_repr, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[repr(u8)]
 pub enum IntentScope {
     ProcessData = 0,
+    KeyRotation = 1,
+    Revocation = 2,
+    Denial = 3,
+    CommittedData = 4,
 }
 
 impl<T: Serialize + Debug> IntentMessage<T> {
@@ -53,14 +62,15 @@ impl<T: Serialize + Debug> IntentMessage<T> {
 }
 
 /// Wrapper struct containing the response (the intent message) and signature.
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ProcessedDataResponse<T> {
     pub response: T,
     pub signature: String,
 }
 
 /// Wrapper struct containing the request payload.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ProcessDataRequest<T> {
     pub payload: T,
 }
@@ -86,60 +96,591 @@ pub fn to_signed_response<T: Serialize + Clone>(
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum ProofError {
+    #[error("malformed proof: {0}")]
+    Malformed(String),
+    #[error("signature does not verify against the enclave key")]
+    InvalidSignature,
+    #[error("proof is signed under intent scope {found:?}, expected {expected:?}")]
+    UnexpectedIntent {
+        expected: IntentScope,
+        found: IntentScope,
+    },
+    #[error("proof timestamp {timestamp_ms} is outside [{not_before_ms}, {not_after_ms}]")]
+    TimestampOutOfBounds {
+        timestamp_ms: u64,
+        not_before_ms: u64,
+        not_after_ms: u64,
+    },
+}
+
+/// Accepted range of the signed `timestamp_ms`, both ends inclusive.
+#[derive(Debug, Clone, Copy)]
+pub struct TimestampBounds {
+    pub not_before_ms: u64,
+    pub not_after_ms: u64,
+}
+
+impl TimestampBounds {
+    pub fn unbounded() -> Self {
+        Self {
+            not_before_ms: 0,
+            not_after_ms: u64::MAX,
+        }
+    }
+}
+
+/// Verifies a response produced by `to_signed_response` offline: rebuilds the BCS
+/// bytes of the intent message, checks the signature against `public_key`, then
+/// the intent scope and that the timestamp lies within `bounds`.
+pub fn verify_signed_response<T: Serialize>(
+    public_key: &Ed25519PublicKey,
+    signed: &ProcessedDataResponse<IntentMessage<T>>,
+    scope: IntentScope,
+    bounds: &TimestampBounds,
+) -> Result<(), ProofError> {
+    let message = bcs::to_bytes(&signed.response).expect("should not fail");
+    let signature = Hex::decode(&signed.signature)
+        .ok()
+        .and_then(|bytes| Ed25519Signature::from_bytes(&bytes).ok())
+        .ok_or_else(|| {
+            ProofError::Malformed("signature must be a hex Ed25519 signature".to_string())
+        })?;
+    public_key
+        .verify(&message, &signature)
+        .map_err(|_| ProofError::InvalidSignature)?;
+
+    if signed.response.intent != scope {
+        return Err(ProofError::UnexpectedIntent {
+            expected: scope,
+            found: signed.response.intent,
+        });
+    }
+    let timestamp_ms = signed.response.timestamp_ms;
+    if timestamp_ms < bounds.not_before_ms || timestamp_ms > bounds.not_after_ms {
+        return Err(ProofError::TimestampOutOfBounds {
+            timestamp_ms,
+            not_before_ms: bounds.not_before_ms,
+            not_after_ms: bounds.not_after_ms,
+        });
+    }
+    Ok(())
+}
+
+/// ==== ENCLAVE SIGNING KEYS ====
+/// Number of retired public keys kept around for verifying older responses.
+const MAX_RETIRED_KEYS: usize = 16;
+
+/// Enclave signing keypair along with its metadata.
+pub struct EnclaveKey {
+    /// First 8 bytes of SHA-256 over the public key, hex encoded.
+    pub key_id: String,
+    pub keypair: Ed25519KeyPair,
+    pub created_at_ms: u64,
+}
+
+impl EnclaveKey {
+    pub fn generate(created_at_ms: u64) -> Self {
+        let keypair = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        Self {
+            key_id: key_id(keypair.public()),
+            keypair,
+            created_at_ms,
+        }
+    }
+}
+
+fn key_id(pk: &Ed25519PublicKey) -> String {
+    Hex::encode(&Sha256::digest(pk.as_bytes())[..8])
+}
+
+/// Public half of a key that has been rotated out.
+#[derive(Clone)]
+pub struct RetiredKey {
+    pub key_id: String,
+    pub public_key: Ed25519PublicKey,
+    pub created_at_ms: u64,
+    pub retired_at_ms: u64,
+}
+
+/// Message signed by the outgoing key to vouch for its successor.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct KeyHandover {
+    pub previous_key_id: String,
+    pub new_key_id: String,
+    pub new_public_key: Vec<u8>,
+    pub activated_at_ms: u64,
+}
+
+/// Current signing key plus the history needed to verify earlier signatures.
+pub struct KeyRing {
+    pub current: Arc<EnclaveKey>,
+    /// Handover that introduced `current`; None for the boot key.
+    pub handover: Option<ProcessedDataResponse<IntentMessage<KeyHandover>>>,
+    /// Retired keys, most recent last.
+    pub retired: Vec<RetiredKey>,
+}
+
+impl KeyRing {
+    pub fn new(current: EnclaveKey) -> Self {
+        Self {
+            current: Arc::new(current),
+            handover: None,
+            retired: Vec::new(),
+        }
+    }
+
+    /// Replaces the current key with a fresh one and returns the handover
+    /// message signed by the outgoing key under `IntentScope::KeyRotation`.
+    pub fn rotate(&mut self, now_ms: u64) -> ProcessedDataResponse<IntentMessage<KeyHandover>> {
+        let next = EnclaveKey::generate(now_ms);
+        let handover = KeyHandover {
+            previous_key_id: self.current.key_id.clone(),
+            new_key_id: next.key_id.clone(),
+            new_public_key: next.keypair.public().as_bytes().to_vec(),
+            activated_at_ms: now_ms,
+        };
+        let signed = to_signed_response(
+            &self.current.keypair,
+            handover,
+            now_ms,
+            IntentScope::KeyRotation,
+        );
+
+        let previous = std::mem::replace(&mut self.current, Arc::new(next));
+        self.retired.push(RetiredKey {
+            key_id: previous.key_id.clone(),
+            public_key: previous.keypair.public().clone(),
+            created_at_ms: previous.created_at_ms,
+            retired_at_ms: now_ms,
+        });
+        if self.retired.len() > MAX_RETIRED_KEYS {
+            self.retired.remove(0);
+        }
+        self.handover = Some(signed.clone());
+        signed
+    }
+}
+
+/// Milliseconds since the Unix epoch, as signed into intents and records.
+pub fn current_timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0))
+        .as_millis() as u64
+}
+
+/// Host-only endpoint that rotates the enclave signing key. Refused until the KYC
+/// config is loaded: the Seal bootstrap signs with the current key, which has to
+/// be the one attested and registered on-chain, so rotating first would break it.
+/// After a rotation the new key must be registered on-chain again.
+#[utoipa::path(
+    post,
+    path = "/rotate_key",
+    tag = "host",
+    responses(
+        (status = 200, description = "Handover signed by the previous key", body = ProcessedDataResponse<IntentMessage<KeyHandover>>),
+        (status = 400, description = "KYC config not loaded yet", body = ErrorResponse),
+    )
+)]
+pub async fn rotate_key(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<KeyHandover>>>, EnclaveError> {
+    crate::app::ensure_runtime(&state).await?;
+    let handover = state.keys.write().await.rotate(current_timestamp_ms());
+    info!(
+        "rotated signing key to {}",
+        handover.response.data.new_key_id
+    );
+    Ok(Json(handover))
+}
+
+/// One entry of the key set, loosely following RFC 8037 OKP JWKs.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct EnclaveJwk {
+    pub kid: String,
+    pub kty: String,
+    pub crv: String,
+    /// Base64url encoded public key.
+    pub x: String,
+    /// Hex encoded public key, as used elsewhere in the API.
+    pub pk: String,
+    /// Either "current" or "retired".
+    pub status: String,
+    pub created_at_ms: u64,
+    pub retired_at_ms: Option<u64>,
+}
+
+/// Response for the key set endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct KeySetResponse {
+    pub keys: Vec<EnclaveJwk>,
+    /// Handover signed by the previous key for the current one, if rotated.
+    pub handover: Option<ProcessedDataResponse<IntentMessage<KeyHandover>>>,
+}
+
+fn jwk(
+    kid: &str,
+    pk: &Ed25519PublicKey,
+    status: &str,
+    created_at_ms: u64,
+    retired_at_ms: Option<u64>,
+) -> EnclaveJwk {
+    EnclaveJwk {
+        kid: kid.to_string(),
+        kty: "OKP".to_string(),
+        crv: "Ed25519".to_string(),
+        x: Base64::encode(pk.as_bytes())
+            .trim_end_matches('=')
+            .replace('+', "-")
+            .replace('/', "_"),
+        pk: Hex::encode(pk.as_bytes()),
+        status: status.to_string(),
+        created_at_ms,
+        retired_at_ms,
+    }
+}
+
+/// Endpoint that lists the current and retired enclave signing keys.
+#[utoipa::path(
+    get,
+    path = "/keys",
+    tag = "enclave",
+    responses((status = 200, body = KeySetResponse))
+)]
+pub async fn get_keys(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<KeySetResponse>, EnclaveError> {
+    let ring = state.keys.read().await;
+    let mut keys = vec![jwk(
+        &ring.current.key_id,
+        ring.current.keypair.public(),
+        "current",
+        ring.current.created_at_ms,
+        None,
+    )];
+    keys.extend(ring.retired.iter().rev().map(|key| {
+        jwk(
+            &key.key_id,
+            &key.public_key,
+            "retired",
+            key.created_at_ms,
+            Some(key.retired_at_ms),
+        )
+    }));
+    Ok(Json(KeySetResponse {
+        keys,
+        handover: ring.handover.clone(),
+    }))
+}
+
 /// ==== HEALTHCHECK, GET ATTESTASTION ENDPOINT IMPL ====
+/// Maximum nonce length accepted by the NSM driver.
+const MAX_NONCE_BYTES: usize = 512;
+
+/// Query parameters for get attestation.
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetAttestationRequest {
+    /// Optional hex encoded nonce supplied by the verifier for freshness.
+    pub nonce: Option<String>,
+}
+
 /// Response for get attestation.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GetAttestationResponse {
     /// Attestation document serialized in Hex.
     pub attestation: String,
+    /// Runtime config the attestation `user_data` commits to, if one is loaded.
+    pub commitment: Option<RuntimeCommitment>,
 }
 
-/// Endpoint that returns an attestation committed
-/// to the enclave's public key.
-#[cfg(target_os = "linux")]
+fn decode_nonce(nonce: Option<&str>) -> Result<Option<Vec<u8>>, EnclaveError> {
+    let Some(nonce) = nonce else {
+        return Ok(None);
+    };
+    let bytes = Hex::decode(nonce.trim_start_matches("0x"))
+        .map_err(|e| EnclaveError::GenericError(format!("Invalid nonce encoding: {}", e)))?;
+    if bytes.is_empty() || bytes.len() > MAX_NONCE_BYTES {
+        return Err(EnclaveError::GenericError(format!(
+            "nonce must be between 1 and {} bytes",
+            MAX_NONCE_BYTES
+        )));
+    }
+    Ok(Some(bytes))
+}
+
+/// Endpoint that returns an attestation committed to the enclave's public key,
+/// the caller-supplied nonce and, once loaded, the active KYC runtime config.
+#[utoipa::path(
+    get,
+    path = "/get_attestation",
+    tag = "enclave",
+    params(GetAttestationRequest),
+    responses(
+        (status = 200, body = GetAttestationResponse),
+        (status = 400, description = "Invalid nonce or attestation failure", body = ErrorResponse),
+    )
+)]
 pub async fn get_attestation(
     State(state): State<Arc<AppState>>,
+    Query(params): Query<GetAttestationRequest>,
 ) -> Result<Json<GetAttestationResponse>, EnclaveError> {
     info!("get attestation called");
 
-    let pk = state.eph_kp.public();
-    let fd = driver::nsm_init();
+    let nonce = decode_nonce(params.nonce.as_deref())?;
+    let commitment = state
+        .kyc_runtime
+        .read()
+        .await
+        .as_ref()
+        .map(|runtime| runtime.commitment());
+    let user_data = commitment
+        .as_ref()
+        .map(|commitment| commitment.digest().to_vec());
 
-    // Send attestation request to NSM driver with public key set.
-    let request = NsmRequest::Attestation {
-        user_data: None,
-        nonce: None,
-        public_key: Some(ByteBuf::from(pk.as_bytes().to_vec())),
-    };
+    let key = state.signing_key().await;
+    let pk = key.keypair.public();
+    let document = state.attestor.attest(pk.as_bytes(), user_data, nonce)?;
 
-    let response = driver::nsm_process_request(fd, request);
-    match response {
-        NsmResponse::Attestation { document } => {
-            driver::nsm_exit(fd);
-            Ok(Json(GetAttestationResponse {
-                attestation: Hex::encode(document),
-            }))
+    Ok(Json(GetAttestationResponse {
+        attestation: Hex::encode(document),
+        commitment,
+    }))
+}
+
+/// PCR0/1/2 of the running enclave image, read from the NSM at boot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnclaveMeasurement {
+    pub pcr0: Vec<u8>,
+    pub pcr1: Vec<u8>,
+    pub pcr2: Vec<u8>,
+}
+
+impl EnclaveMeasurement {
+    /// Hex encoding of PCR0 || PCR1 || PCR2, the value signed as `teeMeasurement`.
+    pub fn to_hex(&self) -> String {
+        format!(
+            "0x{}{}{}",
+            Hex::encode(&self.pcr0),
+            Hex::encode(&self.pcr1),
+            Hex::encode(&self.pcr2)
+        )
+    }
+}
+
+/// Source of attestation documents and PCR measurements. The Nitro implementation
+/// talks to the NSM driver; with the `dev-attestor` feature a software stand-in is
+/// used so the server runs on machines without one.
+pub trait Attestor: Send + Sync {
+    /// Reads PCR0, PCR1 and PCR2 of the running image.
+    fn measurement(&self) -> Result<EnclaveMeasurement, EnclaveError>;
+
+    /// Produces a serialized attestation document binding the given fields.
+    fn attest(
+        &self,
+        public_key: &[u8],
+        user_data: Option<Vec<u8>>,
+        nonce: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, EnclaveError>;
+}
+
+/// Returns the attestor selected at compile time.
+pub fn default_attestor() -> Box<dyn Attestor> {
+    #[cfg(feature = "dev-attestor")]
+    {
+        Box::new(DevAttestor::new())
+    }
+    #[cfg(not(feature = "dev-attestor"))]
+    {
+        Box::new(NitroAttestor)
+    }
+}
+
+/// Attestor backed by the AWS Nitro Secure Module.
+pub struct NitroAttestor;
+
+#[cfg(target_os = "linux")]
+impl Attestor for NitroAttestor {
+    fn measurement(&self) -> Result<EnclaveMeasurement, EnclaveError> {
+        let fd = driver::nsm_init();
+        let mut pcrs = Vec::with_capacity(3);
+        for index in 0..3u16 {
+            match driver::nsm_process_request(fd, NsmRequest::DescribePCR { index }) {
+                NsmResponse::DescribePCR { data, .. } => pcrs.push(data),
+                other => {
+                    driver::nsm_exit(fd);
+                    return Err(EnclaveError::GenericError(format!(
+                        "unexpected NSM response for PCR{}: {:?}",
+                        index, other
+                    )));
+                }
+            }
         }
-        _ => {
-            driver::nsm_exit(fd);
-            Err(EnclaveError::GenericError(
+        driver::nsm_exit(fd);
+        let pcr2 = pcrs.pop().expect("three PCRs read");
+        let pcr1 = pcrs.pop().expect("three PCRs read");
+        let pcr0 = pcrs.pop().expect("three PCRs read");
+        Ok(EnclaveMeasurement { pcr0, pcr1, pcr2 })
+    }
+
+    fn attest(
+        &self,
+        public_key: &[u8],
+        user_data: Option<Vec<u8>>,
+        nonce: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, EnclaveError> {
+        let fd = driver::nsm_init();
+
+        // Send attestation request to NSM driver with public key, nonce and config commitment set.
+        let request = NsmRequest::Attestation {
+            user_data: user_data.map(ByteBuf::from),
+            nonce: nonce.map(ByteBuf::from),
+            public_key: Some(ByteBuf::from(public_key.to_vec())),
+        };
+
+        let response = driver::nsm_process_request(fd, request);
+        driver::nsm_exit(fd);
+        match response {
+            NsmResponse::Attestation { document } => Ok(document),
+            _ => Err(EnclaveError::GenericError(
                 "unexpected response".to_string(),
-            ))
+            )),
         }
     }
 }
 
 #[cfg(not(target_os = "linux"))]
-pub async fn get_attestation(
-    _state: State<Arc<AppState>>,
-) -> Result<Json<GetAttestationResponse>, EnclaveError> {
-    Err(EnclaveError::GenericError(
-        "Attestation is only available on Linux/Nitro builds".to_string(),
-    ))
+impl Attestor for NitroAttestor {
+    fn measurement(&self) -> Result<EnclaveMeasurement, EnclaveError> {
+        Err(EnclaveError::GenericError(
+            "PCRs are only available on Linux/Nitro builds".to_string(),
+        ))
+    }
+
+    fn attest(
+        &self,
+        _public_key: &[u8],
+        _user_data: Option<Vec<u8>>,
+        _nonce: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, EnclaveError> {
+        Err(EnclaveError::GenericError(
+            "Attestation is only available on Linux/Nitro builds".to_string(),
+        ))
+    }
+}
+
+/// Module id stamped on every document produced by [`DevAttestor`].
+#[cfg(feature = "dev-attestor")]
+pub const DEV_ATTESTOR_MODULE_ID: &str = "dev-attestor-INSECURE-not-a-nitro-enclave";
+
+/// Software attestor for development and CI. It emits a COSE_Sign1 document laid
+/// out like a Nitro one, but signed with EdDSA by a key generated at boot, with
+/// all-zero PCRs (as in Nitro debug mode) and no AWS certificate chain. Verifiers
+/// and the onchain registry reject it by construction.
+#[cfg(feature = "dev-attestor")]
+pub struct DevAttestor {
+    signing_key: Ed25519KeyPair,
+}
+
+#[cfg(feature = "dev-attestor")]
+impl DevAttestor {
+    /// COSE algorithm identifier for EdDSA.
+    const COSE_ALG_EDDSA: i8 = -8;
+    const PCR_LEN: usize = 48;
+
+    pub fn new() -> Self {
+        info!("using the INSECURE software dev attestor");
+        Self {
+            signing_key: Ed25519KeyPair::generate(&mut rand::thread_rng()),
+        }
+    }
+}
+
+#[cfg(feature = "dev-attestor")]
+impl Default for DevAttestor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Payload layout of a Nitro attestation document.
+#[cfg(feature = "dev-attestor")]
+#[derive(Serialize)]
+struct DevAttestationDoc {
+    module_id: &'static str,
+    digest: &'static str,
+    timestamp: u64,
+    pcrs: std::collections::BTreeMap<u16, ByteBuf>,
+    certificate: ByteBuf,
+    cabundle: Vec<ByteBuf>,
+    public_key: Option<ByteBuf>,
+    user_data: Option<ByteBuf>,
+    nonce: Option<ByteBuf>,
+}
+
+#[cfg(feature = "dev-attestor")]
+fn to_cbor<T: Serialize>(value: &T) -> Result<Vec<u8>, EnclaveError> {
+    serde_cbor::to_vec(value)
+        .map_err(|e| EnclaveError::GenericError(format!("Failed to encode dev attestation: {}", e)))
+}
+
+#[cfg(feature = "dev-attestor")]
+impl Attestor for DevAttestor {
+    fn measurement(&self) -> Result<EnclaveMeasurement, EnclaveError> {
+        Ok(EnclaveMeasurement {
+            pcr0: vec![0; Self::PCR_LEN],
+            pcr1: vec![0; Self::PCR_LEN],
+            pcr2: vec![0; Self::PCR_LEN],
+        })
+    }
+
+    fn attest(
+        &self,
+        public_key: &[u8],
+        user_data: Option<Vec<u8>>,
+        nonce: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, EnclaveError> {
+        let doc = DevAttestationDoc {
+            module_id: DEV_ATTESTOR_MODULE_ID,
+            digest: "SHA384",
+            timestamp: current_timestamp_ms(),
+            pcrs: (0..3)
+                .map(|index| (index, ByteBuf::from(vec![0; Self::PCR_LEN])))
+                .collect(),
+            certificate: ByteBuf::from(self.signing_key.public().as_bytes().to_vec()),
+            cabundle: vec![],
+            public_key: Some(ByteBuf::from(public_key.to_vec())),
+            user_data: user_data.map(ByteBuf::from),
+            nonce: nonce.map(ByteBuf::from),
+        };
+        let payload = to_cbor(&doc)?;
+        let protected = to_cbor(&std::collections::BTreeMap::from([(
+            1,
+            Self::COSE_ALG_EDDSA,
+        )]))?;
+
+        // COSE Sig_structure: ["Signature1", protected, external_aad, payload].
+        let sig_structure = to_cbor(&(
+            "Signature1",
+            ByteBuf::from(protected.clone()),
+            ByteBuf::new(),
+            ByteBuf::from(payload.clone()),
+        ))?;
+        let signature = self.signing_key.sign(&sig_structure);
+
+        to_cbor(&(
+            ByteBuf::from(protected),
+            std::collections::BTreeMap::<i8, ()>::new(),
+            ByteBuf::from(payload),
+            ByteBuf::from(signature.as_ref().to_vec()),
+        ))
+    }
 }
 
 /// Health check response.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct HealthCheckResponse {
     /// Hex encoded public key booted on enclave.
     pub pk: String,
@@ -149,10 +690,20 @@ pub struct HealthCheckResponse {
 
 /// Endpoint that health checks the enclave connectivity to all
 /// domains and returns the enclave's public key.
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "enclave",
+    responses(
+        (status = 200, body = HealthCheckResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
 pub async fn health_check(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<HealthCheckResponse>, EnclaveError> {
-    let pk = state.eph_kp.public();
+    let key = state.signing_key().await;
+    let pk = key.keypair.public();
 
     // Create HTTP client with timeout
     let client = Client::builder()
@@ -232,3 +783,136 @@ pub async fn health_check(
         endpoints_status,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use tokio::sync::RwLock;
+
+    fn state(runtime: Option<crate::app::KycRuntime>) -> Arc<AppState> {
+        Arc::new(AppState {
+            keys: RwLock::new(KeyRing::new(EnclaveKey::generate(1))),
+            attestor: Box::new(NitroAttestor),
+            measurement: None,
+            walrus_client: Client::new(),
+            kyc_runtime: RwLock::new(runtime),
+            kyc_jobs: Default::default(),
+            rate_limiter: Default::default(),
+        })
+    }
+
+    fn runtime() -> crate::app::KycRuntime {
+        let raw = format!(
+            "walrus_aggregator_url: https://aggregator.example\n\
+             enclave_measurement: \"0x{}\"\n\
+             providers:\n\
+             - provider_id: p1\n  \
+               public_key: \"0x43f632546d79b4c2100ce26b87014175866cc092845eb816b7c05d3d53d7b5ad\"\n",
+            "00".repeat(144)
+        );
+        crate::app::FileKycConfig::parse(&raw)
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn handover_is_signed_by_the_outgoing_key() {
+        let mut ring = KeyRing::new(EnclaveKey::generate(1));
+        let previous = ring.current.clone();
+        let handover = ring.rotate(2);
+        let data = &handover.response.data;
+        assert_eq!(data.previous_key_id, previous.key_id);
+        assert_eq!(data.new_key_id, ring.current.key_id);
+        assert_eq!(
+            data.new_public_key,
+            ring.current.keypair.public().as_bytes()
+        );
+        assert_eq!(data.activated_at_ms, 2);
+
+        let bounds = TimestampBounds::unbounded();
+        verify_signed_response(
+            previous.keypair.public(),
+            &handover,
+            IntentScope::KeyRotation,
+            &bounds,
+        )
+        .unwrap();
+        assert!(verify_signed_response(
+            ring.current.keypair.public(),
+            &handover,
+            IntentScope::KeyRotation,
+            &bounds
+        )
+        .is_err());
+        assert!(verify_signed_response(
+            previous.keypair.public(),
+            &handover,
+            IntentScope::ProcessData,
+            &bounds
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn retired_keys_are_capped() {
+        let mut ring = KeyRing::new(EnclaveKey::generate(0));
+        let mut key_ids = vec![ring.current.key_id.clone()];
+        for now_ms in 1..=MAX_RETIRED_KEYS as u64 + 2 {
+            ring.rotate(now_ms);
+            key_ids.push(ring.current.key_id.clone());
+        }
+        assert_eq!(ring.retired.len(), MAX_RETIRED_KEYS);
+        // The oldest keys are dropped first; the latest retired key is the one
+        // replaced last.
+        let retired: Vec<&String> = ring.retired.iter().map(|key| &key.key_id).collect();
+        let expected: Vec<&String> = key_ids[2..key_ids.len() - 1].iter().collect();
+        assert_eq!(retired, expected);
+        assert_eq!(
+            ring.retired.last().unwrap().retired_at_ms,
+            MAX_RETIRED_KEYS as u64 + 2
+        );
+    }
+
+    #[tokio::test]
+    async fn key_set_lists_current_then_retired_keys() {
+        let state = state(Some(runtime()));
+        let Json(handover) = rotate_key(State(state.clone())).await.unwrap();
+        let Json(second) = rotate_key(State(state.clone())).await.unwrap();
+
+        let Json(key_set) = get_keys(State(state.clone())).await.unwrap();
+        let ring = state.keys.read().await;
+        let statuses: Vec<&str> = key_set.keys.iter().map(|k| k.status.as_str()).collect();
+        assert_eq!(statuses, ["current", "retired", "retired"]);
+        let current = &key_set.keys[0];
+        assert_eq!(current.kid, ring.current.key_id);
+        assert_eq!(
+            current.pk,
+            Hex::encode(ring.current.keypair.public().as_bytes())
+        );
+        assert_eq!(
+            (current.kty.as_str(), current.crv.as_str()),
+            ("OKP", "Ed25519")
+        );
+        assert_eq!(current.retired_at_ms, None);
+        assert_eq!(key_set.keys[1].kid, ring.retired[1].key_id);
+        assert_eq!(key_set.keys[2].kid, handover.response.data.previous_key_id);
+        assert!(key_set.keys[1].retired_at_ms.is_some());
+        assert_eq!(second.response.data.new_key_id, ring.current.key_id);
+        assert_eq!(
+            key_set.handover.unwrap().response.data.new_key_id,
+            second.response.data.new_key_id
+        );
+    }
+
+    #[tokio::test]
+    async fn rotation_waits_for_the_kyc_config() {
+        let state = state(None);
+        let boot_key_id = state.signing_key().await.key_id.clone();
+        let response = rotate_key(State(state.clone())).await.unwrap_err();
+        assert_eq!(response.into_response().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(state.signing_key().await.key_id, boot_key_id);
+    }
+}
@@ -1,14 +1,16 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use axum::http::header::RETRY_AFTER;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use fastcrypto::ed25519::Ed25519KeyPair;
 use reqwest::Client;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::sync::Arc;
 use tokio::sync::RwLock;
+use utoipa::ToSchema;
 
 mod apps {
     pub mod kyc_server;
@@ -22,24 +24,80 @@ pub mod common;
 
 /// App state shared across handlers.
 pub struct AppState {
-    /// Ephemeral keypair on boot
-    pub eph_kp: Ed25519KeyPair,
+    /// Enclave signing keys; generated on boot and replaced on rotation
+    pub keys: RwLock<common::KeyRing>,
+    /// Attestation backend (Nitro NSM, or the software dev attestor)
+    pub attestor: Box<dyn common::Attestor>,
+    /// PCRs read from the attestor on boot; None when none is available
+    pub measurement: Option<common::EnclaveMeasurement>,
     /// Reusable HTTP client for Walrus + Nautilus requests
     pub walrus_client: Client,
     /// In-memory runtime config loaded via Seal or filesystem
     pub kyc_runtime: RwLock<Option<app::KycRuntime>>,
+    /// Background jobs submitted via /process_data_async
+    pub kyc_jobs: app::JobStore,
+    /// Token buckets and concurrency cap for credential requests
+    pub rate_limiter: app::RateLimiter,
+}
+
+impl AppState {
+    /// Returns the key currently used for signing responses.
+    pub async fn signing_key(&self) -> Arc<common::EnclaveKey> {
+        self.keys.read().await.current.clone()
+    }
 }
 
 /// Implement IntoResponse for EnclaveError.
 impl IntoResponse for EnclaveError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            EnclaveError::GenericError(e) => (StatusCode::BAD_REQUEST, e),
+        let status = match &self {
+            EnclaveError::GenericError(_) | EnclaveError::InvalidRequest(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            EnclaveError::Denied { .. } => StatusCode::FORBIDDEN,
+            EnclaveError::RateLimited {
+                retry_after_secs, ..
+            } => {
+                let retry_after = retry_after_secs.to_string();
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(RETRY_AFTER, retry_after)],
+                    Json(ErrorResponse::from(self)),
+                )
+                    .into_response();
+            }
+        };
+        (status, Json(ErrorResponse::from(self))).into_response()
+    }
+}
+
+impl From<EnclaveError> for ErrorResponse {
+    fn from(error: EnclaveError) -> Self {
+        let mut body = ErrorResponse {
+            error: String::new(),
+            violations: Vec::new(),
+            denial: None,
+            retry_after_secs: None,
         };
-        let body = Json(json!({
-            "error": error_message,
-        }));
-        (status, body).into_response()
+        match error {
+            EnclaveError::GenericError(error) => body.error = error,
+            EnclaveError::InvalidRequest(violations) => {
+                body.error = "Invalid request".to_string();
+                body.violations = violations;
+            }
+            EnclaveError::Denied { message, denial } => {
+                body.error = message;
+                body.denial = Some(denial);
+            }
+            EnclaveError::RateLimited {
+                message,
+                retry_after_secs,
+            } => {
+                body.error = message;
+                body.retry_after_secs = Some(retry_after_secs);
+            }
+        }
+        body
     }
 }
 
@@ -47,14 +105,68 @@ impl IntoResponse for EnclaveError {
 #[derive(Debug)]
 pub enum EnclaveError {
     GenericError(String),
+    /// Request failed input validation; lists every offending field.
+    InvalidRequest(Vec<FieldViolation>),
+    /// Request refused by policy; `denial` is the enclave-signed record of it.
+    Denied {
+        message: String,
+        denial: serde_json::Value,
+    },
+    /// Request exceeded a rate limit; retry after `retry_after_secs`.
+    RateLimited {
+        message: String,
+        retry_after_secs: u64,
+    },
 }
 
 impl fmt::Display for EnclaveError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             EnclaveError::GenericError(e) => write!(f, "{}", e),
+            EnclaveError::InvalidRequest(violations) => {
+                write!(f, "Invalid request:")?;
+                for violation in violations {
+                    write!(f, " {}: {};", violation.field, violation.message)?;
+                }
+                Ok(())
+            }
+            EnclaveError::Denied { message, .. } | EnclaveError::RateLimited { message, .. } => {
+                write!(f, "{}", message)
+            }
         }
     }
 }
 
 impl std::error::Error for EnclaveError {}
+
+/// JSON body of every error response.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+    /// Every offending field, for `Invalid request` errors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<FieldViolation>,
+    /// Enclave-signed denial (`IntentScope::Denial`), for 403 responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denial: Option<serde_json::Value>,
+    /// Seconds to wait before retrying, for rate limited requests; mirrors the
+    /// `Retry-After` header, which batch entries do not get.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+/// A single invalid request field and why it was rejected.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldViolation {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldViolation {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldViolation {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
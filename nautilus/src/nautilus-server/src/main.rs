@@ -3,20 +3,48 @@
 
 use anyhow::Result;
 use axum::{routing::get, routing::post, Router};
-use fastcrypto::{ed25519::Ed25519KeyPair, traits::KeyPair};
-use nautilus_server::app::{process_data, spawn_host_init_server};
-use nautilus_server::common::{get_attestation, health_check};
+use nautilus_server::app::{
+    get_job, metrics, openapi_json, process_data, process_data_async, process_data_batch,
+    revoke_kyc, spawn_host_init_server,
+};
+use nautilus_server::common::{
+    current_timestamp_ms, default_attestor, get_attestation, get_keys, health_check, EnclaveKey,
+    KeyRing,
+};
 use nautilus_server::AppState;
 use reqwest::Client;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
-use tracing::info;
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let eph_kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+    let boot_key = EnclaveKey::generate(current_timestamp_ms());
+    info!("signing key {}", boot_key.key_id);
+
+    let attestor = default_attestor();
+    let measurement = match attestor.measurement() {
+        Ok(measurement) => {
+            info!("enclave measurement {}", measurement.to_hex());
+            Some(measurement)
+        }
+        // Without PCRs a config could claim any measurement and have it signed
+        // into every credential, so this is only allowed when asked for.
+        Err(e) if env_flag("NAUTILUS_ALLOW_UNMEASURED", false) => {
+            warn!(
+                "enclave measurement unavailable, config value will be unverified: {}",
+                e
+            );
+            None
+        }
+        Err(e) => anyhow::bail!(
+            "enclave measurement unavailable: {}. Build with the dev-attestor feature or set \
+             NAUTILUS_ALLOW_UNMEASURED=1 to run outside an enclave",
+            e
+        ),
+    };
 
     let walrus_client = Client::builder()
         .timeout(Duration::from_secs(10))
@@ -24,9 +52,13 @@ async fn main() -> Result<()> {
         .expect("failed to construct Walrus HTTP client");
 
     let state = Arc::new(AppState {
-        eph_kp,
+        keys: RwLock::new(KeyRing::new(boot_key)),
+        attestor,
+        measurement,
         walrus_client,
         kyc_runtime: RwLock::new(None),
+        kyc_jobs: Default::default(),
+        rate_limiter: Default::default(),
     });
 
     if should_spawn_seal_host() {
@@ -40,7 +72,14 @@ async fn main() -> Result<()> {
         .route("/", get(ping))
         .route("/get_attestation", get(get_attestation))
         .route("/process_data", post(process_data))
+        .route("/process_data_batch", post(process_data_batch))
+        .route("/process_data_async", post(process_data_async))
+        .route("/jobs/:job_id", get(get_job))
+        .route("/revoke_kyc", post(revoke_kyc))
         .route("/health_check", get(health_check))
+        .route("/keys", get(get_keys))
+        .route("/openapi.json", get(openapi_json))
+        .route("/metrics", get(metrics))
         .with_state(state)
         .layer(cors);
 
@@ -56,7 +95,11 @@ async fn ping() -> &'static str {
 }
 
 fn should_spawn_seal_host() -> bool {
-    std::env::var("NAUTILUS_ENABLE_SEAL_HOST")
+    env_flag("NAUTILUS_ENABLE_SEAL_HOST", true)
+}
+
+fn env_flag(name: &str, default: bool) -> bool {
+    std::env::var(name)
         .map(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
-        .unwrap_or(true)
+        .unwrap_or(default)
 }
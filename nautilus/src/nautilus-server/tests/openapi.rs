@@ -0,0 +1,23 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use nautilus_server::app::ApiDoc;
+use utoipa::OpenApi;
+
+/// The checked-in `openapi.json` must match the document generated from the
+/// code, so API changes show up in review. Regenerate it with
+/// `UPDATE_OPENAPI=1 cargo test --test openapi`.
+#[test]
+fn openapi_json_is_up_to_date() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+    let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(path, &generated).unwrap();
+        return;
+    }
+    let checked_in = std::fs::read_to_string(path).unwrap_or_default();
+    assert!(
+        checked_in == generated,
+        "openapi.json is out of date; rerun with UPDATE_OPENAPI=1 and commit the result"
+    );
+}
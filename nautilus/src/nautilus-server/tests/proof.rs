@@ -0,0 +1,156 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PrivateKey};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use nautilus_server::app::{KycProof, KycResponsePayload, PepMatchConfidence, PredicateResult};
+use nautilus_server::common::{
+    to_signed_response, verify_signed_response, IntentMessage, IntentScope, ProcessedDataResponse,
+    ProofError, TimestampBounds,
+};
+
+const TIMESTAMP_MS: u64 = 1_750_000_000_000;
+
+fn keypair() -> Ed25519KeyPair {
+    Ed25519PrivateKey::from_bytes(&[7; 32]).unwrap().into()
+}
+
+fn payload() -> KycResponsePayload {
+    KycResponsePayload {
+        userWallet: "0x00000000000000000000000000000000000000000000000000000000000000ab"
+            .to_string(),
+        providerId: "provider-a".to_string(),
+        kycLevel: 2,
+        isPep: false,
+        isSanctioned: false,
+        blobId: "blob-1".to_string(),
+        docHash: "0x".to_string() + &"11".repeat(32),
+        walrusCid: "walrus://blob-1".to_string(),
+        teeMeasurement: "0x".to_string() + &"22".repeat(48),
+        nationality: "DE".to_string(),
+        cardArtCid: "bafycardart".to_string(),
+        issuedAtMs: TIMESTAMP_MS,
+        expiresAtMs: TIMESTAMP_MS + 86_400_000,
+        sanctionsListVersion: Some("sdn-2025-06-01".to_string()),
+        pepMatchConfidence: PepMatchConfidence::Low,
+        pepListVersion: None,
+        predicates: vec![PredicateResult {
+            name: "age_over_18".to_string(),
+            value: true,
+        }],
+    }
+}
+
+fn signed() -> ProcessedDataResponse<IntentMessage<KycResponsePayload>> {
+    to_signed_response(
+        &keypair(),
+        payload(),
+        TIMESTAMP_MS,
+        IntentScope::ProcessData,
+    )
+}
+
+/// The layout `src/lib/nautilus.ts` produces: the signed intent message followed
+/// by the BCS `vector<u8>` signature.
+fn bcs_hex(signed: &ProcessedDataResponse<IntentMessage<KycResponsePayload>>) -> String {
+    let mut bytes = bcs::to_bytes(&signed.response).unwrap();
+    bytes.extend(bcs::to_bytes(&Hex::decode(&signed.signature).unwrap()).unwrap());
+    Hex::encode(bytes)
+}
+
+/// Compares `generated` with a checked-in fixture, rewriting it when
+/// `UPDATE_FIXTURES` is set.
+fn fixture(name: &str, generated: &str) -> String {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    if std::env::var_os("UPDATE_FIXTURES").is_some() {
+        std::fs::write(&path, generated).unwrap();
+    }
+    let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        checked_in == generated,
+        "{} is out of date; rerun with UPDATE_FIXTURES=1 and commit the result",
+        name
+    );
+    checked_in
+}
+
+fn verify(proof: &KycProof) -> Result<(), ProofError> {
+    proof.verify(
+        keypair().public(),
+        Some(IntentScope::ProcessData),
+        &TimestampBounds::unbounded(),
+    )
+}
+
+fn assert_round_trips(proof: &KycProof) {
+    let KycProof::ProcessData(parsed) = proof else {
+        panic!("expected a ProcessData proof, got {:?}", proof.scope());
+    };
+    assert_eq!(
+        serde_json::to_value(parsed).unwrap(),
+        serde_json::to_value(signed()).unwrap()
+    );
+    verify(proof).unwrap();
+}
+
+#[test]
+fn signed_response_verifies() {
+    let signed = signed();
+    let pk = keypair().public().clone();
+    let bounds = TimestampBounds::unbounded();
+    verify_signed_response(&pk, &signed, IntentScope::ProcessData, &bounds).unwrap();
+
+    assert!(matches!(
+        verify_signed_response(&pk, &signed, IntentScope::Denial, &bounds),
+        Err(ProofError::UnexpectedIntent { .. })
+    ));
+    let stale = TimestampBounds {
+        not_before_ms: TIMESTAMP_MS + 1,
+        not_after_ms: u64::MAX,
+    };
+    assert!(matches!(
+        verify_signed_response(&pk, &signed, IntentScope::ProcessData, &stale),
+        Err(ProofError::TimestampOutOfBounds { .. })
+    ));
+
+    let mut tampered = signed.clone();
+    tampered.response.data.isSanctioned = true;
+    assert!(matches!(
+        verify_signed_response(&pk, &tampered, IntentScope::ProcessData, &bounds),
+        Err(ProofError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn json_fixture_round_trips() {
+    let generated = serde_json::to_string_pretty(&signed()).unwrap() + "\n";
+    let json = fixture("kyc_proof.json", &generated);
+    assert_round_trips(&KycProof::parse(&json, None).unwrap());
+
+    let tampered = json.replace("\"kycLevel\": 2", "\"kycLevel\": 3");
+    assert_ne!(tampered, json);
+    assert!(matches!(
+        verify(&KycProof::parse(&tampered, None).unwrap()),
+        Err(ProofError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn bcs_hex_fixture_round_trips() {
+    let signed = signed();
+    let hex = fixture("kyc_proof.hex", &(bcs_hex(&signed) + "\n"));
+    assert_round_trips(&KycProof::parse(&hex, None).unwrap());
+    assert_round_trips(&KycProof::parse(&format!("0x{}", hex.trim()), None).unwrap());
+
+    // The intent message alone, with the signature passed separately.
+    let message = Hex::encode(bcs::to_bytes(&signed.response).unwrap());
+    assert_round_trips(&KycProof::parse(&message, Some(&signed.signature)).unwrap());
+
+    let mut bytes = Hex::decode(hex.trim()).unwrap();
+    bytes[1] ^= 1;
+    assert!(matches!(
+        verify(&KycProof::parse(&Hex::encode(bytes), None).unwrap()),
+        Err(ProofError::InvalidSignature)
+    ));
+}
@@ -0,0 +1,406 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline verification of AWS Nitro Enclaves attestation documents.
+//!
+//! A document is a COSE_Sign1 structure whose payload is a CBOR map carrying the
+//! PCRs, the enclave-chosen `public_key`, `user_data` and `nonce`, plus the
+//! certificate chain that signed it. Verification checks the chain against the
+//! bundled AWS Nitro root certificate, the ES384 signature over the payload and
+//! the document timestamp, without touching the network.
+
+use ciborium::value::Value;
+use p384::ecdsa::signature::Verifier;
+use p384::ecdsa::{Signature, VerifyingKey};
+use std::collections::BTreeMap;
+use x509_cert::der::oid::ObjectIdentifier;
+use x509_cert::der::{Decode, DecodePem, Encode};
+use x509_cert::Certificate;
+
+/// AWS Nitro Enclaves root certificate (CN=aws.nitro-enclaves), as published by AWS.
+/// SHA-256 fingerprint: 641A0321A3E244EFE456463195D606317ED7CDCC3C1756E09893F3C68F79BB5B.
+pub const AWS_NITRO_ROOT_PEM: &str = include_str!("../aws_nitro_root.pem");
+
+/// COSE algorithm identifier for ECDSA w/ SHA-384.
+const COSE_ALG_ES384: i128 = -35;
+/// CBOR tag marking a COSE_Sign1 structure.
+const COSE_SIGN1_TAG: u64 = 18;
+/// ecdsa-with-SHA384, the only certificate signature algorithm used by the Nitro PKI.
+const ECDSA_WITH_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.3");
+/// Tolerated clock drift between the enclave and the verifying machine.
+const ALLOWED_CLOCK_SKEW_MS: u64 = 60_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum VerifyError {
+    #[error("malformed COSE_Sign1 structure: {0}")]
+    InvalidCose(String),
+    #[error("malformed attestation payload: {0}")]
+    InvalidPayload(String),
+    #[error("invalid certificate: {0}")]
+    InvalidCertificate(String),
+    #[error("certificate chain does not end in the trusted root")]
+    UntrustedRoot,
+    #[error("certificate {subject} is not valid at {timestamp_ms}")]
+    CertificateExpired { subject: String, timestamp_ms: u64 },
+    #[error("signature verification failed: {0}")]
+    InvalidSignature(String),
+    #[error("attestation timestamp {timestamp_ms} is outside the accepted window")]
+    StaleTimestamp { timestamp_ms: u64 },
+    #[error("attestation nonce does not match the expected nonce")]
+    NonceMismatch,
+}
+
+/// Knobs for a single verification.
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    /// DER encoding of the trusted root. Defaults to the bundled AWS Nitro root.
+    pub root_der: Vec<u8>,
+    /// Reference time in Unix milliseconds, used for certificate validity and freshness.
+    pub now_ms: u64,
+    /// Reject documents older than this, relative to `now_ms`.
+    pub max_age_ms: Option<u64>,
+    /// Require the document to carry exactly this nonce.
+    pub expected_nonce: Option<Vec<u8>>,
+}
+
+impl VerifyOptions {
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            root_der: aws_nitro_root_der(),
+            now_ms,
+            max_age_ms: None,
+            expected_nonce: None,
+        }
+    }
+
+    /// Trusts the given PEM certificate instead of the bundled AWS Nitro root.
+    pub fn with_root_pem(mut self, pem: &str) -> Result<Self, VerifyError> {
+        self.root_der = Certificate::from_pem(pem)
+            .and_then(|cert| cert.to_der())
+            .map_err(|e| VerifyError::InvalidCertificate(e.to_string()))?;
+        Ok(self)
+    }
+}
+
+/// Fields extracted from a verified attestation document.
+#[derive(Debug, Clone)]
+pub struct VerifiedAttestation {
+    pub module_id: String,
+    pub digest: String,
+    pub timestamp_ms: u64,
+    pub pcrs: BTreeMap<u64, Vec<u8>>,
+    pub public_key: Option<Vec<u8>>,
+    pub user_data: Option<Vec<u8>>,
+    pub nonce: Option<Vec<u8>>,
+}
+
+impl VerifiedAttestation {
+    /// Returns the value of a PCR register, if present.
+    pub fn pcr(&self, index: u64) -> Option<&[u8]> {
+        self.pcrs.get(&index).map(Vec::as_slice)
+    }
+}
+
+/// DER encoding of the bundled AWS Nitro root certificate.
+pub fn aws_nitro_root_der() -> Vec<u8> {
+    Certificate::from_pem(AWS_NITRO_ROOT_PEM)
+        .and_then(|cert| cert.to_der())
+        .expect("bundled root certificate is valid")
+}
+
+/// Parses and verifies a raw (CBOR) attestation document.
+pub fn verify_attestation(
+    document: &[u8],
+    options: &VerifyOptions,
+) -> Result<VerifiedAttestation, VerifyError> {
+    let cose = CoseSign1::parse(document)?;
+    let payload = AttestationPayload::parse(&cose.payload)?;
+
+    if payload.digest != "SHA384" {
+        return Err(VerifyError::InvalidPayload(format!(
+            "unsupported digest {}",
+            payload.digest
+        )));
+    }
+
+    let leaf = verify_chain(&payload, &options.root_der)?;
+
+    let leaf_key = verifying_key(&leaf)?;
+    let signature = Signature::from_slice(&cose.signature)
+        .map_err(|e| VerifyError::InvalidSignature(e.to_string()))?;
+    leaf_key
+        .verify(&cose.sig_structure()?, &signature)
+        .map_err(|e| VerifyError::InvalidSignature(e.to_string()))?;
+
+    check_freshness(payload.timestamp_ms, options)?;
+
+    if let Some(expected) = &options.expected_nonce {
+        if payload.nonce.as_ref() != Some(expected) {
+            return Err(VerifyError::NonceMismatch);
+        }
+    }
+
+    Ok(VerifiedAttestation {
+        module_id: payload.module_id,
+        digest: payload.digest,
+        timestamp_ms: payload.timestamp_ms,
+        pcrs: payload.pcrs,
+        public_key: payload.public_key,
+        user_data: payload.user_data,
+        nonce: payload.nonce,
+    })
+}
+
+fn check_freshness(timestamp_ms: u64, options: &VerifyOptions) -> Result<(), VerifyError> {
+    if timestamp_ms > options.now_ms.saturating_add(ALLOWED_CLOCK_SKEW_MS) {
+        return Err(VerifyError::StaleTimestamp { timestamp_ms });
+    }
+    if let Some(max_age_ms) = options.max_age_ms {
+        if options.now_ms.saturating_sub(timestamp_ms) > max_age_ms {
+            return Err(VerifyError::StaleTimestamp { timestamp_ms });
+        }
+    }
+    Ok(())
+}
+
+/// Walks `cabundle` (root first) down to the leaf certificate, checking every
+/// signature and validity window at the document timestamp. Returns the leaf.
+fn verify_chain(payload: &AttestationPayload, root_der: &[u8]) -> Result<Certificate, VerifyError> {
+    let root = payload
+        .cabundle
+        .first()
+        .ok_or_else(|| VerifyError::InvalidPayload("empty cabundle".to_string()))?;
+    if root.as_slice() != root_der {
+        return Err(VerifyError::UntrustedRoot);
+    }
+
+    let mut issuer = parse_certificate(root)?;
+    check_validity(&issuer, payload.timestamp_ms)?;
+    check_signature(&issuer, &issuer)?;
+
+    for der in payload
+        .cabundle
+        .iter()
+        .skip(1)
+        .chain([&payload.certificate])
+    {
+        let cert = parse_certificate(der)?;
+        check_validity(&cert, payload.timestamp_ms)?;
+        check_signature(&cert, &issuer)?;
+        issuer = cert;
+    }
+    Ok(issuer)
+}
+
+fn parse_certificate(der: &[u8]) -> Result<Certificate, VerifyError> {
+    Certificate::from_der(der).map_err(|e| VerifyError::InvalidCertificate(e.to_string()))
+}
+
+fn check_validity(cert: &Certificate, timestamp_ms: u64) -> Result<(), VerifyError> {
+    let validity = &cert.tbs_certificate.validity;
+    let not_before = validity.not_before.to_unix_duration().as_millis() as u64;
+    let not_after = validity.not_after.to_unix_duration().as_millis() as u64;
+    if timestamp_ms < not_before || timestamp_ms > not_after {
+        return Err(VerifyError::CertificateExpired {
+            subject: cert.tbs_certificate.subject.to_string(),
+            timestamp_ms,
+        });
+    }
+    Ok(())
+}
+
+fn check_signature(cert: &Certificate, issuer: &Certificate) -> Result<(), VerifyError> {
+    if cert.signature_algorithm.oid != ECDSA_WITH_SHA384 {
+        return Err(VerifyError::InvalidCertificate(format!(
+            "unsupported signature algorithm {}",
+            cert.signature_algorithm.oid
+        )));
+    }
+    let tbs = cert
+        .tbs_certificate
+        .to_der()
+        .map_err(|e| VerifyError::InvalidCertificate(e.to_string()))?;
+    let signature = Signature::from_der(cert.signature.raw_bytes())
+        .map_err(|e| VerifyError::InvalidSignature(e.to_string()))?;
+    verifying_key(issuer)?
+        .verify(&tbs, &signature)
+        .map_err(|_| {
+            VerifyError::InvalidSignature(format!(
+                "{} is not signed by {}",
+                cert.tbs_certificate.subject, issuer.tbs_certificate.subject
+            ))
+        })
+}
+
+fn verifying_key(cert: &Certificate) -> Result<VerifyingKey, VerifyError> {
+    let spki = &cert.tbs_certificate.subject_public_key_info;
+    VerifyingKey::from_sec1_bytes(spki.subject_public_key.raw_bytes())
+        .map_err(|e| VerifyError::InvalidCertificate(e.to_string()))
+}
+
+/// The four COSE_Sign1 fields, as defined in RFC 9052 section 4.2.
+struct CoseSign1 {
+    protected: Vec<u8>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    fn parse(document: &[u8]) -> Result<Self, VerifyError> {
+        let value: Value = ciborium::de::from_reader(document)
+            .map_err(|e| VerifyError::InvalidCose(e.to_string()))?;
+        let value = match value {
+            Value::Tag(COSE_SIGN1_TAG, inner) => *inner,
+            other => other,
+        };
+        let Value::Array(items) = value else {
+            return Err(VerifyError::InvalidCose("expected an array".to_string()));
+        };
+        let [protected, _unprotected, payload, signature]: [Value; 4] = items
+            .try_into()
+            .map_err(|_| VerifyError::InvalidCose("expected four elements".to_string()))?;
+        let cose = CoseSign1 {
+            protected: into_bytes(protected, "protected header")
+                .map_err(VerifyError::InvalidCose)?,
+            payload: into_bytes(payload, "payload").map_err(VerifyError::InvalidCose)?,
+            signature: into_bytes(signature, "signature").map_err(VerifyError::InvalidCose)?,
+        };
+        cose.check_algorithm()?;
+        Ok(cose)
+    }
+
+    fn check_algorithm(&self) -> Result<(), VerifyError> {
+        let header: Value = ciborium::de::from_reader(self.protected.as_slice())
+            .map_err(|e| VerifyError::InvalidCose(e.to_string()))?;
+        let alg = header
+            .as_map()
+            .and_then(|map| {
+                map.iter()
+                    .find(|(key, _)| key.as_integer().map(i128::from) == Some(1))
+            })
+            .and_then(|(_, alg)| alg.as_integer())
+            .map(i128::from);
+        match alg {
+            Some(COSE_ALG_ES384) => Ok(()),
+            other => Err(VerifyError::InvalidCose(format!(
+                "unsupported algorithm {:?}",
+                other
+            ))),
+        }
+    }
+
+    /// The `Sig_structure` the enclave signed: ["Signature1", protected, external_aad, payload].
+    fn sig_structure(&self) -> Result<Vec<u8>, VerifyError> {
+        let structure = Value::Array(vec![
+            Value::Text("Signature1".to_string()),
+            Value::Bytes(self.protected.clone()),
+            Value::Bytes(vec![]),
+            Value::Bytes(self.payload.clone()),
+        ]);
+        let mut out = Vec::new();
+        ciborium::ser::into_writer(&structure, &mut out)
+            .map_err(|e| VerifyError::InvalidCose(e.to_string()))?;
+        Ok(out)
+    }
+}
+
+/// Attestation document body, see the `AttestationDoc` layout in aws-nitro-enclaves-nsm-api.
+struct AttestationPayload {
+    module_id: String,
+    digest: String,
+    timestamp_ms: u64,
+    pcrs: BTreeMap<u64, Vec<u8>>,
+    certificate: Vec<u8>,
+    cabundle: Vec<Vec<u8>>,
+    public_key: Option<Vec<u8>>,
+    user_data: Option<Vec<u8>>,
+    nonce: Option<Vec<u8>>,
+}
+
+impl AttestationPayload {
+    fn parse(payload: &[u8]) -> Result<Self, VerifyError> {
+        let value: Value = ciborium::de::from_reader(payload)
+            .map_err(|e| VerifyError::InvalidPayload(e.to_string()))?;
+        let Value::Map(entries) = value else {
+            return Err(VerifyError::InvalidPayload("expected a map".to_string()));
+        };
+        let mut fields: BTreeMap<String, Value> = BTreeMap::new();
+        for (key, value) in entries {
+            if let Value::Text(key) = key {
+                fields.insert(key, value);
+            }
+        }
+        let mut take = |name: &str| {
+            fields
+                .remove(name)
+                .ok_or_else(|| VerifyError::InvalidPayload(format!("missing field {}", name)))
+        };
+
+        let module_id = match take("module_id")? {
+            Value::Text(text) => text,
+            _ => return Err(VerifyError::InvalidPayload("module_id".to_string())),
+        };
+        let digest = match take("digest")? {
+            Value::Text(text) => text,
+            _ => return Err(VerifyError::InvalidPayload("digest".to_string())),
+        };
+        let timestamp_ms = take("timestamp")?
+            .as_integer()
+            .and_then(|ts| u64::try_from(ts).ok())
+            .ok_or_else(|| VerifyError::InvalidPayload("timestamp".to_string()))?;
+        let pcrs = match take("pcrs")? {
+            Value::Map(entries) => entries
+                .into_iter()
+                .map(|(index, value)| {
+                    let index = index
+                        .as_integer()
+                        .and_then(|index| u64::try_from(index).ok())
+                        .ok_or_else(|| VerifyError::InvalidPayload("pcr index".to_string()))?;
+                    let value = into_bytes(value, "pcr").map_err(VerifyError::InvalidPayload)?;
+                    Ok((index, value))
+                })
+                .collect::<Result<BTreeMap<_, _>, VerifyError>>()?,
+            _ => return Err(VerifyError::InvalidPayload("pcrs".to_string())),
+        };
+        let certificate =
+            into_bytes(take("certificate")?, "certificate").map_err(VerifyError::InvalidPayload)?;
+        let cabundle = match take("cabundle")? {
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| into_bytes(item, "cabundle").map_err(VerifyError::InvalidPayload))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(VerifyError::InvalidPayload("cabundle".to_string())),
+        };
+        let public_key = optional_bytes(fields.remove("public_key"), "public_key")?;
+        let user_data = optional_bytes(fields.remove("user_data"), "user_data")?;
+        let nonce = optional_bytes(fields.remove("nonce"), "nonce")?;
+
+        Ok(AttestationPayload {
+            module_id,
+            digest,
+            timestamp_ms,
+            pcrs,
+            certificate,
+            cabundle,
+            public_key,
+            user_data,
+            nonce,
+        })
+    }
+}
+
+fn into_bytes(value: Value, field: &str) -> Result<Vec<u8>, String> {
+    match value {
+        Value::Bytes(bytes) => Ok(bytes),
+        _ => Err(format!("{} must be a byte string", field)),
+    }
+}
+
+fn optional_bytes(value: Option<Value>, field: &str) -> Result<Option<Vec<u8>>, VerifyError> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => into_bytes(value, field)
+            .map(Some)
+            .map_err(VerifyError::InvalidPayload),
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Verification of a checked-in attestation document. The fixture has the
+//! layout the NSM emits (an untagged COSE_Sign1 with an ES384 signature and a
+//! `cabundle` of root and intermediate), but its chain ends in the P-384 test
+//! root `test_root.pem` rather than the AWS one: documents chaining to AWS can
+//! only be produced inside a Nitro Enclave.
+
+use nitro_verifier::{verify_attestation, VerifyError, VerifyOptions};
+
+const DOCUMENT: &[u8] = include_bytes!("fixtures/attestation.cbor");
+const TEST_ROOT_PEM: &str = include_str!("fixtures/test_root.pem");
+/// `timestamp` of the fixture, 2025-06-15T01:00:00Z. Its leaf certificate is
+/// valid from 00:00 to 03:00 that day.
+const TIMESTAMP_MS: u64 = 1_749_949_200_000;
+const NONCE: &[u8] = b"nautilus-test-nonce";
+const FIVE_MINUTES_MS: u64 = 5 * 60 * 1000;
+
+fn options(now_ms: u64) -> VerifyOptions {
+    VerifyOptions::new(now_ms)
+        .with_root_pem(TEST_ROOT_PEM)
+        .unwrap()
+}
+
+#[test]
+fn valid_document_verifies() {
+    let mut options = options(TIMESTAMP_MS + 1_000);
+    options.max_age_ms = Some(FIVE_MINUTES_MS);
+    options.expected_nonce = Some(NONCE.to_vec());
+
+    let verified = verify_attestation(DOCUMENT, &options).unwrap();
+    assert_eq!(
+        verified.module_id,
+        "i-0123456789abcdef0-enc0123456789abcdef"
+    );
+    assert_eq!(verified.digest, "SHA384");
+    assert_eq!(verified.timestamp_ms, TIMESTAMP_MS);
+    assert_eq!(verified.pcr(0), Some(&[1u8; 48][..]));
+    assert_eq!(verified.pcr(1), Some(&[2u8; 48][..]));
+    assert_eq!(verified.pcr(2), Some(&[3u8; 48][..]));
+    assert_eq!(verified.pcrs.len(), 16);
+    assert_eq!(verified.public_key, Some((0..32).collect()));
+    assert_eq!(
+        verified.user_data.as_deref(),
+        Some(&b"config-commitment"[..])
+    );
+    assert_eq!(verified.nonce.as_deref(), Some(NONCE));
+}
+
+#[test]
+fn tampered_signature_is_rejected() {
+    // The signature is the last field of the COSE_Sign1 array.
+    let mut document = DOCUMENT.to_vec();
+    *document.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        verify_attestation(&document, &options(TIMESTAMP_MS)),
+        Err(VerifyError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn tampered_payload_is_rejected() {
+    let position = DOCUMENT
+        .windows(NONCE.len())
+        .position(|window| window == NONCE)
+        .unwrap();
+    let mut document = DOCUMENT.to_vec();
+    document[position] ^= 1;
+    assert!(matches!(
+        verify_attestation(&document, &options(TIMESTAMP_MS)),
+        Err(VerifyError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn wrong_root_is_rejected() {
+    assert!(matches!(
+        verify_attestation(DOCUMENT, &VerifyOptions::new(TIMESTAMP_MS)),
+        Err(VerifyError::UntrustedRoot)
+    ));
+}
+
+#[test]
+fn wrong_nonce_is_rejected() {
+    let mut options = options(TIMESTAMP_MS);
+    options.expected_nonce = Some(b"another-nonce".to_vec());
+    assert!(matches!(
+        verify_attestation(DOCUMENT, &options),
+        Err(VerifyError::NonceMismatch)
+    ));
+}
+
+#[test]
+fn documents_older_than_max_age_are_rejected() {
+    let mut options = options(TIMESTAMP_MS + FIVE_MINUTES_MS);
+    options.max_age_ms = Some(FIVE_MINUTES_MS);
+    verify_attestation(DOCUMENT, &options).unwrap();
+
+    options.now_ms += 1;
+    assert!(matches!(
+        verify_attestation(DOCUMENT, &options),
+        Err(VerifyError::StaleTimestamp {
+            timestamp_ms: TIMESTAMP_MS
+        })
+    ));
+}
+
+#[test]
+fn documents_from_the_future_are_rejected() {
+    assert!(matches!(
+        verify_attestation(DOCUMENT, &options(TIMESTAMP_MS - 10 * 60 * 1000)),
+        Err(VerifyError::StaleTimestamp { .. })
+    ));
+}
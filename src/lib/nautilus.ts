@@ -34,18 +34,47 @@ export interface KycRequestPayload {
   userWallet: string;
   providerId: string;
   kycLevel: number;
-  isPep: boolean;
-  isSanctioned: boolean;
   blobId: string;
   docHash: string;
-  walrusCid: string;
+  walrusBlobObject?: string;
   nationality: string;
   cardArtCid: string;
-  imageUrl: string;
+  providerSignature?: string;
+  documentExpiresAtMs?: number;
+  fullName?: string;
+  /** `YYYY-MM-DD`. */
+  dateOfBirth?: string;
+  commitFields?: string[];
+  residenceCountry?: string;
+  requestedPredicates?: string[];
+  holderPublicKey?: string;
+}
+
+export interface KycPredicateResult {
+  name: string;
+  value: boolean;
 }
 
-export interface KycResponsePayload extends KycRequestPayload {
+/** Payload the enclave signs, in the order of its BCS encoding. */
+export interface KycResponsePayload {
+  userWallet: string;
+  providerId: string;
+  kycLevel: number;
+  isPep: boolean;
+  isSanctioned: boolean;
+  blobId: string;
+  docHash: string;
+  walrusCid: string;
   teeMeasurement: string;
+  nationality: string;
+  cardArtCid: string;
+  issuedAtMs: number;
+  expiresAtMs: number;
+  sanctionsListVersion: string | null;
+  /** 0 (no match) to 3 (closest band). */
+  pepMatchConfidence: number;
+  pepListVersion: string | null;
+  predicates: KycPredicateResult[];
 }
 
 export interface NautilusKycProof {
@@ -61,23 +90,35 @@ export interface NautilusClient {
   ): Promise<NautilusProcessResponse<KycResponsePayload>>;
 }
 
-const kycDataBcs = bcs.struct("KycResponseData", {
-  user_wallet: bcs.Address,
+// Mirrors the enclave's `IntentMessage<KycResponsePayload>` exactly: the
+// signature is over these bytes, so field order and types must not change.
+const kycDataBcs = bcs.struct("KycResponsePayload", {
+  user_wallet: bcs.string(),
   provider_id: bcs.string(),
   kyc_level: bcs.u8(),
   is_pep: bcs.bool(),
   is_sanctioned: bcs.bool(),
   blob_id: bcs.string(),
-  doc_hash: bcs.vector(bcs.u8()),
+  doc_hash: bcs.string(),
   walrus_cid: bcs.string(),
+  tee_measurement: bcs.string(),
   nationality: bcs.string(),
   card_art_cid: bcs.string(),
-  image_url: bcs.string(),
-  tee_measurement: bcs.vector(bcs.u8()),
+  issued_at_ms: bcs.u64(),
+  expires_at_ms: bcs.u64(),
+  sanctions_list_version: bcs.option(bcs.string()),
+  pep_match_confidence: bcs.u8(),
+  pep_list_version: bcs.option(bcs.string()),
+  predicates: bcs.vector(
+    bcs.struct("PredicateResult", {
+      name: bcs.string(),
+      value: bcs.bool(),
+    }),
+  ),
 });
 
-const nautilusResponseBcs = bcs.struct("NautilusResponse", {
-  intent: bcs.u32(),
+const nautilusResponseBcs = bcs.struct("IntentMessage", {
+  intent: bcs.u8(),
   timestamp_ms: bcs.u64(),
   data: kycDataBcs,
 });
@@ -131,24 +172,30 @@ export function buildKycProofFromResponse(
 }
 
 export function serializeKycProof(proof: NautilusKycProof): Uint8Array {
+  const data = proof.response.data;
   return nautilusProofBcs
     .serialize({
       response: {
         intent: proof.response.intent,
         timestamp_ms: BigInt(proof.response.timestamp_ms),
         data: {
-          user_wallet: normalizeAddress(proof.response.data.userWallet),
-          provider_id: proof.response.data.providerId,
-          kyc_level: proof.response.data.kycLevel,
-          is_pep: proof.response.data.isPep,
-          is_sanctioned: proof.response.data.isSanctioned,
-          blob_id: proof.response.data.blobId,
-          doc_hash: hexToBytes(proof.response.data.docHash),
-          walrus_cid: proof.response.data.walrusCid,
-          nationality: proof.response.data.nationality,
-          card_art_cid: proof.response.data.cardArtCid,
-          image_url: proof.response.data.imageUrl,
-          tee_measurement: hexToBytes(proof.response.data.teeMeasurement),
+          user_wallet: data.userWallet,
+          provider_id: data.providerId,
+          kyc_level: data.kycLevel,
+          is_pep: data.isPep,
+          is_sanctioned: data.isSanctioned,
+          blob_id: data.blobId,
+          doc_hash: data.docHash,
+          walrus_cid: data.walrusCid,
+          tee_measurement: data.teeMeasurement,
+          nationality: data.nationality,
+          card_art_cid: data.cardArtCid,
+          issued_at_ms: BigInt(data.issuedAtMs),
+          expires_at_ms: BigInt(data.expiresAtMs),
+          sanctions_list_version: data.sanctionsListVersion,
+          pep_match_confidence: data.pepMatchConfidence,
+          pep_list_version: data.pepListVersion,
+          predicates: data.predicates,
         },
       },
       signature: proof.signature,
@@ -158,44 +205,30 @@ export function serializeKycProof(proof: NautilusKycProof): Uint8Array {
 
 export function deserializeKycProof(bytes: Uint8Array | ArrayBuffer | string): NautilusKycProof {
   const input = typeof bytes === "string" ? hexToBytes(bytes) : new Uint8Array(bytes);
-  const parsed = nautilusProofBcs.parse(input) as unknown as {
-    response: {
-      intent: number;
-      timestamp_ms: bigint;
-        data: {
-          user_wallet: string;
-          provider_id: string;
-          kyc_level: number;
-          is_pep: boolean;
-          is_sanctioned: boolean;
-          blob_id: string;
-          doc_hash: number[];
-          walrus_cid: string;
-          nationality: string;
-          card_art_cid: string;
-          image_url: string;
-          tee_measurement: number[];
-        };
-    };
-    signature: number[];
-  };
+  const parsed = nautilusProofBcs.parse(input);
+  const data = parsed.response.data;
   return {
     response: {
       intent: parsed.response.intent,
       timestamp_ms: Number(parsed.response.timestamp_ms),
       data: {
-        userWallet: parsed.response.data.user_wallet,
-        providerId: parsed.response.data.provider_id,
-        kycLevel: parsed.response.data.kyc_level,
-        isPep: parsed.response.data.is_pep,
-        isSanctioned: parsed.response.data.is_sanctioned,
-        blobId: parsed.response.data.blob_id,
-        docHash: bytesToHex(new Uint8Array(parsed.response.data.doc_hash)),
-        walrusCid: parsed.response.data.walrus_cid,
-        nationality: parsed.response.data.nationality,
-        cardArtCid: parsed.response.data.card_art_cid,
-        imageUrl: parsed.response.data.image_url,
-        teeMeasurement: bytesToHex(new Uint8Array(parsed.response.data.tee_measurement)),
+        userWallet: data.user_wallet,
+        providerId: data.provider_id,
+        kycLevel: data.kyc_level,
+        isPep: data.is_pep,
+        isSanctioned: data.is_sanctioned,
+        blobId: data.blob_id,
+        docHash: data.doc_hash,
+        walrusCid: data.walrus_cid,
+        teeMeasurement: data.tee_measurement,
+        nationality: data.nationality,
+        cardArtCid: data.card_art_cid,
+        issuedAtMs: Number(data.issued_at_ms),
+        expiresAtMs: Number(data.expires_at_ms),
+        sanctionsListVersion: data.sanctions_list_version ?? null,
+        pepMatchConfidence: data.pep_match_confidence,
+        pepListVersion: data.pep_list_version ?? null,
+        predicates: data.predicates,
       },
     },
     signature: new Uint8Array(parsed.signature),
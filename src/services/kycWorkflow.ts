@@ -74,21 +74,17 @@ export async function uploadWalrusDocument(input: WalrusDataInput): Promise<Walr
 export interface NautilusRequestInput {
   walletAddress: string;
   level: number;
-  pep: boolean;
-  sanctioned: boolean;
   blobId: string;
   blobObjectId?: string;
   docHash: string;
   nationality: string;
   cardArtCid: string;
+  fullName?: string;
+  /** `YYYY-MM-DD`. */
+  dateOfBirth?: string;
 }
 
 export async function requestNautilusProof(input: NautilusRequestInput): Promise<NautilusProofArtifacts> {
-  const isHttpArt = input.cardArtCid.startsWith("http://") || input.cardArtCid.startsWith("https://");
-  const imageUrl = isHttpArt
-    ? input.cardArtCid
-    : `${WALRUS_CONFIG.aggregatorUrl.replace(/\/$/, "")}/v1/blobs/${input.cardArtCid}`;
-
   try {
     await nautilusClient.health();
   } catch {
@@ -102,14 +98,13 @@ export async function requestNautilusProof(input: NautilusRequestInput): Promise
       userWallet: normalizeAddress(input.walletAddress),
       providerId: PROVIDER_METADATA.providerId,
       kycLevel: input.level,
-      isPep: input.pep,
-      isSanctioned: input.sanctioned,
       blobId: input.blobId,
       docHash: input.docHash,
-      walrusCid: input.blobObjectId ?? input.blobId,
+      walrusBlobObject: input.blobObjectId,
       nationality: input.nationality,
       cardArtCid: input.cardArtCid,
-      imageUrl,
+      fullName: input.fullName,
+      dateOfBirth: input.dateOfBirth,
     },
   };
 
@@ -215,13 +215,12 @@ export function AppStateProvider({ children }: { children: ReactNode }) {
       const result = await requestNautilusProof({
         walletAddress: wallet.address,
         level: kyc.level === "L2" ? 2 : 1,
-        pep: kyc.form.pep,
-        sanctioned: kyc.form.sanctioned,
         blobId: walrusArtifact.blobId,
         blobObjectId: walrusArtifact.blobObjectId,
         docHash: walrusArtifact.docHash,
         nationality: kyc.form.country,
         cardArtCid: kyc.form.cardArtCid,
+        fullName: kyc.form.fullName.trim() || undefined,
       });
       setProofArtifact(result);
       setKyc((prev) => ({
@@ -241,7 +240,7 @@ export function AppStateProvider({ children }: { children: ReactNode }) {
       setKyc((prev) => ({ ...prev, reviewNotes: message, stage: "rejected" }));
       throw error instanceof Error ? error : new Error(message);
     }
-  }, [kyc.form.pep, kyc.form.sanctioned, kyc.level, wallet.address, walrusArtifact]);
+  }, [kyc.level, wallet.address, walrusArtifact]);
 
   const submitForReview = useCallback(async () => {
     await requestProof();